@@ -4,39 +4,44 @@ use sodiumoxide::crypto::box_;
 use std::{
     cell::RefCell,
     fmt::{Debug, Formatter},
-    sync::Arc,
+    rc::Rc,
 };
 
+use crate::error::ArrowError;
 use crate::lisptype::LispType;
 
 /// Wrapper around Actaeon.
 #[derive(Clone)]
 pub struct Actaeon {
-    pub center: Arc<Center>,
-    pub interface: Arc<Interface>,
-    pub topic: Arc<RefCell<Topic>>,
+    pub center: Rc<Center>,
+    pub interface: Rc<Interface>,
+    pub topic: Rc<RefCell<Topic>>,
 }
 
 impl Actaeon {
     /// Connect to an actaeon network. This function is used internally
     /// and everything is handled by the library.
-    pub fn new(center: &str, remote: &str, port: usize, topic: &str) -> LispType {
+    pub fn new(center: &str, remote: &str, port: usize, topic: &str) -> Result<Self, ArrowError> {
         let (_, secret) = box_::gen_keypair();
         let config = Config::new(20, 1, 100, remote.to_string(), port);
         let center = Center::new(secret, center.to_string(), port);
-        let interface = Interface::new(config, center.clone()).unwrap();
+        let interface =
+            Interface::new(config, center.clone()).map_err(|e| ArrowError::Network {
+                message: format!("{:?}", e),
+                span: None,
+            })?;
 
         let topic = interface.subscribe(&topic.to_string().to_address());
 
-        LispType::Actaeon(Self {
-            center: Arc::new(center),
-            interface: Arc::new(interface),
-            topic: Arc::new(RefCell::new(topic)),
+        Ok(Self {
+            center: Rc::new(center),
+            interface: Rc::new(interface),
+            topic: Rc::new(RefCell::new(topic)),
         })
     }
 
     /// Receive data from actaeon.
-    pub fn receive(&mut self) -> LispType {
+    pub fn receive(&self) -> LispType {
         let mut topic = self.topic.borrow_mut();
         let msg = topic.try_recv();
 
@@ -48,7 +53,7 @@ impl Actaeon {
     }
 
     /// Send data to actaeon.
-    pub fn send(&mut self, send: &str) -> LispType {
+    pub fn send(&self, send: &str) -> LispType {
         let mut topic = self.topic.borrow_mut();
 
         let _ = topic.broadcast(send.as_bytes().to_vec());
@@ -62,3 +67,10 @@ impl Debug for Actaeon {
         Ok(())
     }
 }
+
+impl PartialEq for Actaeon {
+    /// Two values are only equal, if they share the same connection.
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.interface, &other.interface)
+    }
+}
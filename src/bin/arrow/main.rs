@@ -3,7 +3,7 @@ mod repl;
 fn main() {
     let args: Vec<String> = std::env::args().skip(1).collect();
 
-    if args.len() == 0 {
+    if args.is_empty() {
         repl::repl();
     } else {
         let _code = handle_args(args);
@@ -35,11 +35,11 @@ mod tests {
 
     #[test]
     fn test_handle_args() {
-        let test = vec!["--version", "-h", "(message", "\"h\")"]
+        let test = ["--version", "-h", "(message", "\"h\")"]
             .iter()
             .map(|s| s.to_string())
             .collect();
-        let exp: Vec<String> = vec!["(message", "\"h\")"]
+        let exp: Vec<String> = ["(message", "\"h\")"]
             .iter()
             .map(|s| s.to_string())
             .collect();
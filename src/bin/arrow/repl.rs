@@ -1,4 +1,4 @@
-use arrow::tokenize::{ast, create_lisptypes};
+use arrow::error::ArrowError;
 use arrow::Arrow;
 
 const MESSAGE: &str = "Arrow: A LISP dialect.
@@ -21,46 +21,33 @@ pub fn repl() {
         let mut input = String::new();
         print!("> ");
         stdout().flush().unwrap();
-        stdin().read_line(&mut input).unwrap_or_else(|_| {
-            println!("Invalid string.");
-            0
-        });
+        match stdin().read_line(&mut input) {
+            // The end of the input, like after Ctrl-D.
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(_) => {
+                println!("Invalid string.");
+                continue;
+            }
+        }
 
         let input = input.trim();
 
         match input {
+            "" => {}
             "exit" => break,
             "help" => println!("{}", REPL_HELP),
             "version" => println!("{}{}", MESSAGE, env!("CARGO_PKG_VERSION")),
-            _ => {
-                if input.len() > 7 {
-                    match input.trim_start()[0..6].as_ref() {
-                        "(defun" => {
-                            lispfns = lispfns.add_function(&input).unwrap();
-                        }
-                        _ => println!(
-                            "{}",
-                            lispfns.run(&input).unwrap().to_string(&mut vec![]).unwrap()
-                        ),
-                    }
-                } else if input.chars().nth(0).unwrap() == '(' {
-                    let ast = ast(input);
-                    let lisptype = &mut create_lisptypes(ast).unwrap()[0];
-                    println!(
-                        "{}",
-                        lisptype
-                            .run(&mut vec![])
-                            .unwrap()
-                            .to_string(&mut vec![])
-                            .unwrap()
-                    );
-                } else {
-                    println!(
-                        "{}",
-                        lispfns.run(&input).unwrap().to_string(&mut vec![]).unwrap()
-                    )
-                }
-            }
+            _ => match eval(&mut lispfns, input) {
+                Ok(res) => println!("{}", res),
+                Err(e) => println!("Error: {}", e),
+            },
         }
     }
 }
+
+/// Evaluate a single line of input. It runs in the global environment
+/// of `lispfns`, so definitions are kept for the following lines.
+fn eval(lispfns: &mut Arrow, input: &str) -> Result<String, ArrowError> {
+    lispfns.eval(input)?.to_readable()
+}
@@ -0,0 +1,397 @@
+//! Conversions between Rust values and arrow values, for programs
+//! that embed arrow. [IntoLisp] turns a Rust value into a [LispType]
+//! and [FromLisp] reads a Rust value out of one.
+//!
+//! | Rust                          | arrow                               |
+//! |-------------------------------|-------------------------------------|
+//! | integers                      | integer, larger ones are clamped    |
+//! | `f32`, `f64`                  | float, integers are converted       |
+//! | `bool`                        | `t` and `nil`, like in `if`         |
+//! | `String`, `&str`              | string                              |
+//! | `Vec<T>`                      | list, vectors are read as well      |
+//! | `Option<T>`                   | `nil` or the value                  |
+//! | `HashMap<String, T>`          | hash table with string keys         |
+//! | tuples like `(A, B)`          | list with one element for each item |
+//! | `()`                          | `nil`                               |
+//!
+//! # Examples
+//!
+//! ```
+//! use arrow::convert::{FromLisp, IntoLisp};
+//!
+//! let value = vec![(1, "one".to_string()), (2, "two".to_string())].into_lisp();
+//! assert_eq!(value.to_readable().unwrap(), "((1 \"one\") (2 \"two\"))");
+//!
+//! let back: Vec<(i64, String)> = value.into_rust().unwrap();
+//! assert_eq!(back[1], (2, "two".to_string()));
+//! ```
+
+use std::{collections::HashMap, convert::TryFrom, hash::BuildHasher};
+
+use crate::error::ArrowError;
+use crate::expression::Arity;
+use crate::hashtable::{self, HashTable, Test};
+use crate::lisptype::LispType;
+use crate::list;
+use crate::vector;
+
+/// A Rust value, that can be turned into an arrow value.
+pub trait IntoLisp {
+    fn into_lisp(self) -> LispType;
+}
+
+/// A Rust value, that can be read out of an arrow value. Values of
+/// the wrong type are an [ArrowError::Type].
+pub trait FromLisp: Sized {
+    fn from_lisp(value: LispType) -> Result<Self, ArrowError>;
+}
+
+impl LispType {
+    /// Convert the value into a Rust value, like `i64` or `Vec<String>`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use arrow::lisptype::LispType;
+    ///
+    /// let n: i64 = LispType::Integer(3).into_rust().unwrap();
+    /// assert_eq!(n, 3);
+    /// assert!(LispType::Integer(3).into_rust::<String>().is_err());
+    /// ```
+    pub fn into_rust<T: FromLisp>(self) -> Result<T, ArrowError> {
+        T::from_lisp(self)
+    }
+}
+
+impl IntoLisp for LispType {
+    fn into_lisp(self) -> LispType {
+        self
+    }
+}
+
+impl FromLisp for LispType {
+    fn from_lisp(value: LispType) -> Result<Self, ArrowError> {
+        Ok(value)
+    }
+}
+
+/// Integers, that always fit into an arrow integer.
+macro_rules! small_integers {
+    ($($t:ty),*) => {$(
+        impl IntoLisp for $t {
+            fn into_lisp(self) -> LispType {
+                LispType::Integer(i64::from(self))
+            }
+        }
+    )*};
+}
+
+small_integers!(i8, i16, i32, i64, u8, u16, u32);
+
+/// Integers, that can be too large for an arrow integer. The
+/// conversion can't fail, so values outside of its range become the
+/// largest or smallest arrow integer instead.
+macro_rules! large_integers {
+    ($($t:ty),*) => {$(
+        impl IntoLisp for $t {
+            fn into_lisp(self) -> LispType {
+                let limit = if self > 0 { i64::MAX } else { i64::MIN };
+                LispType::Integer(i64::try_from(self).unwrap_or(limit))
+            }
+        }
+    )*};
+}
+
+large_integers!(isize, u64, usize);
+
+/// Integers, that are read out of an arrow integer, if it is in
+/// their range.
+macro_rules! integers {
+    ($($t:ty),*) => {$(
+        impl FromLisp for $t {
+            fn from_lisp(value: LispType) -> Result<Self, ArrowError> {
+                let i = value.int()?;
+                <$t>::try_from(i).map_err(|_| {
+                    ArrowError::invalid(format!(
+                        "{} doesn't fit into {}",
+                        i,
+                        stringify!($t)
+                    ))
+                })
+            }
+        }
+    )*};
+}
+
+integers!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl IntoLisp for f64 {
+    fn into_lisp(self) -> LispType {
+        LispType::Float(self)
+    }
+}
+
+impl IntoLisp for f32 {
+    fn into_lisp(self) -> LispType {
+        LispType::Float(f64::from(self))
+    }
+}
+
+impl FromLisp for f64 {
+    fn from_lisp(value: LispType) -> Result<Self, ArrowError> {
+        value.num()
+    }
+}
+
+impl FromLisp for f32 {
+    fn from_lisp(value: LispType) -> Result<Self, ArrowError> {
+        Ok(value.num()? as f32)
+    }
+}
+
+impl IntoLisp for bool {
+    fn into_lisp(self) -> LispType {
+        LispType::Bool(self)
+    }
+}
+
+impl FromLisp for bool {
+    /// Like in `if`, everything except `nil` is true.
+    fn from_lisp(value: LispType) -> Result<Self, ArrowError> {
+        Ok(value.truthy())
+    }
+}
+
+impl IntoLisp for String {
+    fn into_lisp(self) -> LispType {
+        LispType::String(self)
+    }
+}
+
+impl IntoLisp for &str {
+    fn into_lisp(self) -> LispType {
+        LispType::String(self.to_string())
+    }
+}
+
+impl FromLisp for String {
+    fn from_lisp(value: LispType) -> Result<Self, ArrowError> {
+        match value {
+            LispType::String(s) => Ok(s),
+            other => Err(ArrowError::type_error("string", other.type_name())),
+        }
+    }
+}
+
+impl IntoLisp for () {
+    fn into_lisp(self) -> LispType {
+        LispType::Bool(false)
+    }
+}
+
+impl<T: IntoLisp> IntoLisp for Vec<T> {
+    fn into_lisp(self) -> LispType {
+        list::list(self.into_iter().map(IntoLisp::into_lisp).collect())
+    }
+}
+
+impl<T: FromLisp> FromLisp for Vec<T> {
+    fn from_lisp(value: LispType) -> Result<Self, ArrowError> {
+        vector::sequence(&value)?
+            .into_iter()
+            .map(T::from_lisp)
+            .collect()
+    }
+}
+
+impl<T: IntoLisp> IntoLisp for Option<T> {
+    fn into_lisp(self) -> LispType {
+        self.map_or(LispType::Bool(false), IntoLisp::into_lisp)
+    }
+}
+
+impl<T: FromLisp> FromLisp for Option<T> {
+    fn from_lisp(value: LispType) -> Result<Self, ArrowError> {
+        match value {
+            LispType::Bool(false) => Ok(None),
+            other => T::from_lisp(other).map(Some),
+        }
+    }
+}
+
+impl<T: IntoLisp, S: BuildHasher> IntoLisp for HashMap<String, T, S> {
+    /// The keys are compared with `equal`, so they can be looked up
+    /// with `gethash` from arrow code.
+    fn into_lisp(self) -> LispType {
+        let table = HashTable::new(Test::Equal);
+        for (key, value) in self {
+            table.put(LispType::String(key), value.into_lisp());
+        }
+        LispType::HashTable(table)
+    }
+}
+
+impl<T: FromLisp, S: BuildHasher + Default> FromLisp for HashMap<String, T, S> {
+    fn from_lisp(value: LispType) -> Result<Self, ArrowError> {
+        hashtable::table(&value)?
+            .entries()
+            .into_iter()
+            .map(|(key, value)| Ok((String::from_lisp(key)?, T::from_lisp(value)?)))
+            .collect()
+    }
+}
+
+/// Tuples are lists with one element for every item.
+macro_rules! tuples {
+    ($(($n:expr; $($t:ident),+)),*) => {$(
+        impl<$($t: IntoLisp),+> IntoLisp for ($($t,)+) {
+            #[allow(non_snake_case)]
+            fn into_lisp(self) -> LispType {
+                let ($($t,)+) = self;
+                list::list(vec![$($t.into_lisp()),+])
+            }
+        }
+
+        impl<$($t: FromLisp),+> FromLisp for ($($t,)+) {
+            fn from_lisp(value: LispType) -> Result<Self, ArrowError> {
+                let items = list::elements(&value)?;
+                let found = items.len();
+                let wrong_length = || {
+                    ArrowError::invalid(format!(
+                        "expected a list of {} elements, got {}",
+                        $n, found
+                    ))
+                };
+                if found != $n {
+                    return Err(wrong_length());
+                }
+                let mut items = items.into_iter();
+                Ok(($($t::from_lisp(items.next().ok_or_else(wrong_length)?)?,)+))
+            }
+        }
+
+        impl<$($t: IntoLisp),+> IntoArgs for ($($t,)+) {
+            #[allow(non_snake_case)]
+            fn into_args(self) -> Vec<LispType> {
+                let ($($t,)+) = self;
+                vec![$($t.into_lisp()),+]
+            }
+        }
+
+        impl<F, Res, $($t: FromLisp),+> IntoNative<($($t,)+)> for F
+        where
+            F: Fn($($t),+) -> Result<Res, ArrowError> + 'static,
+            Res: IntoLisp,
+        {
+            fn arity() -> Arity {
+                Arity::Exact($n)
+            }
+
+            #[allow(non_snake_case)]
+            fn call(&self, args: &[LispType]) -> Result<LispType, ArrowError> {
+                let ($($t,)+) = <($($t,)+)>::from_lisp(list::list(args.to_vec()))?;
+                Ok(self($($t),+)?.into_lisp())
+            }
+        }
+    )*};
+}
+
+/// The arguments of a call from Rust, see
+/// [Arrow::call](crate::Arrow::call). A tuple passes every item as
+/// one argument, a slice or a [Vec] of arrow values passes them as
+/// they are.
+pub trait IntoArgs {
+    fn into_args(self) -> Vec<LispType>;
+}
+
+impl IntoArgs for () {
+    fn into_args(self) -> Vec<LispType> {
+        vec![]
+    }
+}
+
+impl IntoArgs for &[LispType] {
+    fn into_args(self) -> Vec<LispType> {
+        self.to_vec()
+    }
+}
+
+impl<const N: usize> IntoArgs for &[LispType; N] {
+    fn into_args(self) -> Vec<LispType> {
+        self.to_vec()
+    }
+}
+
+impl IntoArgs for Vec<LispType> {
+    fn into_args(self) -> Vec<LispType> {
+        self
+    }
+}
+
+/// A Rust closure with typed parameters, that can be registered with
+/// [Arrow::register_fn](crate::Arrow::register_fn). The arguments
+/// are converted with [FromLisp] and the result with [IntoLisp].
+/// `Args` is the tuple of the parameter types.
+pub trait IntoNative<Args> {
+    /// How many arguments the closure takes.
+    fn arity() -> Arity;
+
+    /// Convert the arguments and call the closure.
+    fn call(&self, args: &[LispType]) -> Result<LispType, ArrowError>;
+}
+
+impl<F, Res> IntoNative<()> for F
+where
+    F: Fn() -> Result<Res, ArrowError> + 'static,
+    Res: IntoLisp,
+{
+    fn arity() -> Arity {
+        Arity::Exact(0)
+    }
+
+    fn call(&self, _: &[LispType]) -> Result<LispType, ArrowError> {
+        Ok(self()?.into_lisp())
+    }
+}
+
+tuples!(
+    (1; T1),
+    (2; T1, T2),
+    (3; T1, T2, T3),
+    (4; T1, T2, T3, T4),
+    (5; T1, T2, T3, T4, T5),
+    (6; T1, T2, T3, T4, T5, T6)
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_integer_ranges() {
+        assert_eq!(LispType::Integer(255).into_rust::<u8>().unwrap(), 255);
+        assert!(LispType::Integer(256).into_rust::<u8>().is_err());
+        assert!(LispType::Integer(-1).into_rust::<usize>().is_err());
+        assert!(LispType::Float(1.).into_rust::<i64>().is_err());
+        assert_eq!(LispType::Integer(2).into_rust::<f64>().unwrap(), 2.);
+    }
+
+    #[test]
+    fn test_tuple_lengths() {
+        let value = (1, "a", true).into_lisp();
+        assert_eq!(value.to_readable().unwrap(), "(1 \"a\" t)");
+        assert!(value.clone().into_rust::<(i64, String)>().is_err());
+        assert!(value.into_rust::<(i64, String, bool, bool)>().is_err());
+        assert_eq!((1, 2).into_args().len(), 2);
+    }
+
+    #[test]
+    fn test_nil() {
+        let nil = LispType::Bool(false);
+        assert_eq!(nil.clone().into_rust::<Option<i64>>().unwrap(), None);
+        assert!(nil.clone().into_rust::<Vec<i64>>().unwrap().is_empty());
+        assert!(!nil.clone().into_rust::<bool>().unwrap());
+        assert_eq!(().into_lisp(), nil);
+        assert_eq!(None::<i64>.into_lisp(), nil);
+    }
+}
@@ -0,0 +1,207 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+use crate::error::ArrowError;
+
+use crate::function::Function;
+use crate::lisptype::LispType;
+use crate::macros;
+
+/// A scope, in which variables are defined. Every scope, except the
+/// outermost one, has a parent scope. Variables that aren't defined
+/// in a scope are looked up in its parents, so inner scopes shadow
+/// the variables of outer scopes.
+///
+/// Functions, macros and the variables defined with `defvar`,
+/// `defparameter` and `defconst` are global, so they are always
+/// stored in the outermost scope.
+///
+/// Cloning an [Environment] is cheap, the clone refers to the same
+/// scope.
+///
+/// # Examples
+///
+/// ```
+/// use arrow::environment::Environment;
+/// use arrow::lisptype::LispType;
+///
+/// let global = Environment::default();
+/// global.define("a", LispType::Integer(1));
+///
+/// let local = global.child();
+/// local.define("a", LispType::Integer(2));
+///
+/// assert_eq!(local.get("a"), Some(LispType::Integer(2)));
+/// assert_eq!(global.get("a"), Some(LispType::Integer(1)));
+/// assert_eq!(local.get("b"), None);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Environment(Rc<RefCell<Frame>>);
+
+#[derive(Debug, Default)]
+struct Frame {
+    vars: HashMap<String, LispType>,
+    /// Variables of this scope, that can't be changed.
+    constants: HashSet<String>,
+    functions: HashMap<String, Rc<Function>>,
+    macros: HashMap<String, Rc<Function>>,
+    parent: Option<Environment>,
+}
+
+impl Environment {
+    /// Create a new, empty scope inside this one.
+    pub fn child(&self) -> Self {
+        Self(Rc::new(RefCell::new(Frame {
+            parent: Some(self.clone()),
+            ..Frame::default()
+        })))
+    }
+
+    /// The outermost scope.
+    pub fn root(&self) -> Self {
+        let mut env = self.clone();
+        loop {
+            let parent = env.0.borrow().parent.clone();
+            match parent {
+                Some(parent) => env = parent,
+                None => return env,
+            }
+        }
+    }
+
+    /// Define a variable in this scope. A variable with the same name
+    /// in this scope is replaced.
+    pub fn define(&self, name: impl Into<String>, value: LispType) {
+        self.0.borrow_mut().vars.insert(name.into(), value);
+    }
+
+    /// Get the value of the variable, from the innermost scope that
+    /// defines it.
+    pub fn get(&self, name: &str) -> Option<LispType> {
+        let mut env = self.clone();
+        loop {
+            let parent = {
+                let frame = env.0.borrow();
+                if let Some(value) = frame.vars.get(name) {
+                    return Some(value.clone());
+                }
+                frame.parent.clone()?
+            };
+            env = parent;
+        }
+    }
+
+    /// Define a constant in the outermost scope. It can still be
+    /// shadowed by local variables, but [Environment::set] fails for
+    /// it.
+    pub fn define_constant(&self, name: impl Into<String>, value: LispType) {
+        let name = name.into();
+        let root = self.root();
+        let mut frame = root.0.borrow_mut();
+        frame.constants.insert(name.clone());
+        frame.vars.insert(name, value);
+    }
+
+    /// Change the value of the variable in the innermost scope that
+    /// defines it, like `setq` does. A variable, that isn't defined
+    /// yet, is defined in the outermost scope.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use arrow::environment::Environment;
+    /// use arrow::lisptype::LispType;
+    ///
+    /// let global = Environment::default();
+    /// let local = global.child();
+    /// local.set("a", LispType::Integer(1)).unwrap();
+    /// assert_eq!(global.get("a"), Some(LispType::Integer(1)));
+    ///
+    /// global.define_constant("pi", LispType::Float(3.14));
+    /// assert!(local.set("pi", LispType::Integer(3)).is_err());
+    /// ```
+    pub fn set(&self, name: &str, value: LispType) -> Result<(), ArrowError> {
+        // The innermost scope that defines the variable, or the
+        // outermost scope.
+        let mut env = self.clone();
+        loop {
+            let parent = {
+                let frame = env.0.borrow();
+                if frame.vars.contains_key(name) {
+                    break;
+                }
+                frame.parent.clone()
+            };
+            match parent {
+                Some(parent) => env = parent,
+                None => break,
+            }
+        }
+
+        let mut frame = env.0.borrow_mut();
+        if frame.constants.contains(name) {
+            return Err(ArrowError::invalid(format!(
+                "`{}` is a constant and can't be changed",
+                name
+            )));
+        }
+        frame.vars.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    /// Define a function. It is visible in every scope and replaces
+    /// a macro with the same name.
+    pub fn define_function(&self, function: Function) {
+        let root = self.root();
+        let mut frame = root.0.borrow_mut();
+        frame.macros.remove(&function.name);
+        frame
+            .functions
+            .insert(function.name.clone(), Rc::new(function));
+    }
+
+    /// Get the function with this name.
+    pub fn function(&self, name: &str) -> Option<Rc<Function>> {
+        self.root().0.borrow().functions.get(name).cloned()
+    }
+
+    /// Remove the function with this name. Returns if it was
+    /// defined.
+    pub fn remove_function(&self, name: &str) -> bool {
+        self.root().0.borrow_mut().functions.remove(name).is_some()
+    }
+
+    /// The names of all functions, in alphabetical order.
+    pub fn function_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.root().0.borrow().functions.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Define a macro. Like functions, macros are visible in every
+    /// scope. It replaces a function with the same name.
+    pub fn define_macro(&self, function: Function) {
+        macros::declare(&function.name);
+        let root = self.root();
+        let mut frame = root.0.borrow_mut();
+        frame.functions.remove(&function.name);
+        frame
+            .macros
+            .insert(function.name.clone(), Rc::new(function));
+    }
+
+    /// Get the macro with this name.
+    pub fn macro_function(&self, name: &str) -> Option<Rc<Function>> {
+        self.root().0.borrow().macros.get(name).cloned()
+    }
+}
+
+impl PartialEq for Environment {
+    /// Two environments are only equal, if they are the same scope.
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
@@ -0,0 +1,175 @@
+use std::{error::Error, fmt};
+
+use crate::expression::Arity;
+use crate::lexer::Span;
+
+/// All errors that can happen while reading or running arrow code.
+///
+/// Every variant carries the [Span] of the code that caused it, if
+/// it is known. Errors that happen while running an expression get
+/// the span of the innermost expression attached.
+///
+/// # Examples
+///
+/// ```
+/// use arrow::environment::Environment;
+/// use arrow::error::ArrowError;
+/// use arrow::tokenize::{ast, create_lisptypes};
+///
+/// let code = create_lisptypes(ast("(+ 1 (foo 2))").unwrap()).unwrap();
+/// let err = code[0].run(&Environment::default()).unwrap_err();
+///
+/// match &err {
+///     ArrowError::UnknownFunction { name, span } => {
+///         assert_eq!(name, "foo");
+///         assert_eq!(span.unwrap().start.column, 6);
+///     }
+///     _ => panic!("wrong error"),
+/// }
+/// assert_eq!(err.to_string(), "unknown function `foo` at 1:6");
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub enum ArrowError {
+    /// The source code couldn't be read.
+    Parse { message: String, span: Option<Span> },
+    /// There is no function with this name.
+    UnknownFunction { name: String, span: Option<Span> },
+    /// A function got the wrong number of arguments.
+    Arity {
+        name: String,
+        expected: Arity,
+        found: usize,
+        span: Option<Span>,
+    },
+    /// A value doesn't have the type that was needed.
+    Type {
+        expected: &'static str,
+        found: &'static str,
+        span: Option<Span>,
+    },
+    /// A symbol was used as a variable, but no variable has that name.
+    UnboundVariable { name: String, span: Option<Span> },
+    /// Talking to the actaeon network failed.
+    Network { message: String, span: Option<Span> },
+    /// An argument has the right type, but its value can't be used.
+    InvalidArgument { message: String, span: Option<Span> },
+    /// A calculation failed, e.g. because of a division by zero.
+    Arithmetic { message: String, span: Option<Span> },
+    /// Expressions were nested too deep while running, usually because
+    /// of endless recursion, or a value was nested too deep to print.
+    Depth { span: Option<Span> },
+    /// `return` or `break` was used outside of a loop or function.
+    /// Inside of them, it only stops the loop or function and never
+    /// reaches the caller.
+    Return(Unwind),
+}
+
+/// A `return` or `break`, while it leaves the expressions around it.
+/// It can only be created by arrow itself.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Unwind {
+    span: Option<Span>,
+}
+
+impl ArrowError {
+    /// Shortcut for creating an [ArrowError::Parse].
+    pub fn parse(message: impl Into<String>, span: Option<Span>) -> Self {
+        Self::Parse {
+            message: message.into(),
+            span,
+        }
+    }
+
+    /// Shortcut for creating an [ArrowError::Type].
+    pub fn type_error(expected: &'static str, found: &'static str) -> Self {
+        Self::Type {
+            expected,
+            found,
+            span: None,
+        }
+    }
+
+    /// Shortcut for creating an [ArrowError::InvalidArgument].
+    pub fn invalid(message: impl Into<String>) -> Self {
+        Self::InvalidArgument {
+            message: message.into(),
+            span: None,
+        }
+    }
+
+    /// The signal of a `return` or `break`.
+    pub(crate) fn unwind() -> Self {
+        Self::Return(Unwind { span: None })
+    }
+
+    /// The part of the source code that caused the error.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Self::Parse { span, .. }
+            | Self::UnknownFunction { span, .. }
+            | Self::Arity { span, .. }
+            | Self::Type { span, .. }
+            | Self::UnboundVariable { span, .. }
+            | Self::Network { span, .. }
+            | Self::InvalidArgument { span, .. }
+            | Self::Arithmetic { span, .. }
+            | Self::Depth { span }
+            | Self::Return(Unwind { span }) => *span,
+        }
+    }
+
+    /// Attach a span to the error, if it doesn't have one yet.
+    pub fn with_span(mut self, new: Option<Span>) -> Self {
+        match &mut self {
+            Self::Parse { span, .. }
+            | Self::UnknownFunction { span, .. }
+            | Self::Arity { span, .. }
+            | Self::Type { span, .. }
+            | Self::UnboundVariable { span, .. }
+            | Self::Network { span, .. }
+            | Self::InvalidArgument { span, .. }
+            | Self::Arithmetic { span, .. }
+            | Self::Depth { span }
+            | Self::Return(Unwind { span }) => {
+                if span.is_none() {
+                    *span = new;
+                }
+            }
+        }
+        self
+    }
+}
+
+impl fmt::Display for ArrowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse { message, .. } => write!(f, "parse error: {}", message),
+            Self::UnknownFunction { name, .. } => write!(f, "unknown function `{}`", name),
+            Self::Arity {
+                name,
+                expected,
+                found,
+                ..
+            } => write!(
+                f,
+                "`{}` expects {} arguments, but got {}",
+                name, expected, found
+            ),
+            Self::Type {
+                expected, found, ..
+            } => write!(f, "expected {}, found {}", expected, found),
+            Self::UnboundVariable { name, .. } => write!(f, "unbound variable `{}`", name),
+            Self::Network { message, .. } => write!(f, "network error: {}", message),
+            Self::InvalidArgument { message, .. } => write!(f, "invalid argument: {}", message),
+            Self::Arithmetic { message, .. } => write!(f, "arithmetic error: {}", message),
+            Self::Depth { .. } => write!(f, "expressions are nested too deep"),
+            Self::Return(_) => write!(f, "`return` used outside of a loop or function"),
+        }?;
+        if let Some(span) = self.span() {
+            write!(f, " at {}", span)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ArrowError {}
@@ -1,149 +1,1001 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::fmt;
+
 use crate::actaeon::Actaeon;
+use crate::environment::Environment;
+use crate::error::ArrowError;
+use crate::function::{self, Closure, Parameters};
+use crate::hashtable;
+use crate::lexer::Span;
 use crate::lisptype::LispType;
-use crate::string::Append;
-
-/// Enum where all functions are registered, that arrow knows about.
-#[derive(Clone, Copy, Debug)]
-pub enum Func {
-    Defun,
-    Add,
-    Subtract,
-    Multiply,
-    Concat,
-    Equal,
-    Print,
-    Let,
-    Progn,
-    Return,
-    ActaeonConnect,
-    ActaeonReceive,
-    ActaeonSend,
+use crate::list;
+use crate::macros;
+use crate::number::{self, Number};
+use crate::stack;
+use crate::string;
+use crate::tokenize::compile;
+use crate::vector;
+
+/// Signature of the closures that implement the arrow functions.
+pub type BuiltinFn = dyn Fn(&[LispType], &Environment) -> Result<LispType, ArrowError>;
+
+/// How many arguments a function accepts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Arity {
+    /// Exactly this many arguments.
+    Exact(usize),
+    /// At least this many arguments.
+    Min(usize),
+    /// At least the first and at most the second number of arguments.
+    Range(usize, usize),
+    /// Any number of arguments.
+    Variadic,
+}
+
+impl Arity {
+    /// Check if `n` arguments are accepted.
+    pub fn accepts(&self, n: usize) -> bool {
+        match *self {
+            Self::Exact(e) => n == e,
+            Self::Min(min) => n >= min,
+            Self::Range(min, max) => n >= min && n <= max,
+            Self::Variadic => true,
+        }
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exact(e) => write!(f, "exactly {}", e),
+            Self::Min(min) => write!(f, "at least {}", min),
+            Self::Range(min, max) => write!(f, "between {} and {}", min, max),
+            Self::Variadic => write!(f, "any number of"),
+        }
+    }
+}
+
+/// Declare [Func] together with the name and the [Arity] of every
+/// function. The matches in [Func::new], [Func::name] and
+/// [Func::arity] are generated from the same list, so a function
+/// can't be missing in one of them. The `internal` forms are created
+/// by the compiler and can't be named in arrow code, so they are left
+/// out of [Func::new].
+macro_rules! funcs {
+    (
+        internal { $($internal:ident => ($internal_name:literal, $internal_arity:expr),)* }
+        $($func:ident => ($name:literal, $arity:expr),)*
+    ) => {
+        /// Enum where all functions are registered, that arrow knows about.
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        pub enum Func {
+            $($internal,)*
+            $($func,)*
+        }
+
+        impl Func {
+            /// The builtin function with this name.
+            fn lookup(name: &str) -> Option<Self> {
+                match name {
+                    $($name => Some(Self::$func),)*
+                    _ => None,
+                }
+            }
+
+            /// The name of the function in arrow code.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(Self::$internal => $internal_name,)*
+                    $(Self::$func => $name,)*
+                }
+            }
+
+            /// How many arguments the function accepts.
+            pub fn arity(&self) -> Arity {
+                use Arity::*;
+                match self {
+                    $(Self::$internal => $internal_arity,)*
+                    $(Self::$func => $arity,)*
+                }
+            }
+        }
+    };
+}
+
+funcs! {
+    internal {
+        // A call of a function, that might be a macro. The name is
+        // followed by the quoted data of the arguments and by the
+        // compiled arguments.
+        Call => ("#call", Min(2)),
+        // A call of a function, that isn't a macro. The name is
+        // followed by the compiled arguments.
+        Invoke => ("#invoke", Min(1)),
+    }
+    Defun => ("defun", Min(2)),
+    Defmacro => ("defmacro", Min(2)),
+    Quote => ("quote", Exact(1)),
+    Macroexpand1 => ("macroexpand-1", Exact(1)),
+    Macroexpand => ("macroexpand", Exact(1)),
+    Gensym => ("gensym", Range(0, 1)),
+    Lambda => ("lambda", Min(1)),
+    Function => ("function", Exact(1)),
+    Funcall => ("funcall", Min(1)),
+    Apply => ("apply", Min(2)),
+    Defvar => ("defvar", Range(1, 3)),
+    Defparameter => ("defparameter", Range(2, 3)),
+    Defconst => ("defconst", Range(2, 3)),
+    Setq => ("setq", Variadic),
+    Setf => ("setf", Variadic),
+    Cons => ("cons", Exact(2)),
+    Car => ("car", Exact(1)),
+    Cdr => ("cdr", Exact(1)),
+    List => ("list", Variadic),
+    Append => ("append", Variadic),
+    Reverse => ("reverse", Exact(1)),
+    Length => ("length", Exact(1)),
+    Nth => ("nth", Exact(2)),
+    Nthcdr => ("nthcdr", Exact(2)),
+    Last => ("last", Range(1, 2)),
+    Member => ("member", Exact(2)),
+    Assoc => ("assoc", Exact(2)),
+    Push => ("push", Exact(2)),
+    Pop => ("pop", Exact(1)),
+    MakeHashTable => ("make-hash-table", Variadic),
+    Gethash => ("gethash", Range(2, 3)),
+    Puthash => ("puthash", Exact(3)),
+    Remhash => ("remhash", Exact(2)),
+    Maphash => ("maphash", Exact(2)),
+    HashTableCount => ("hash-table-count", Exact(1)),
+    HashTableKeys => ("hash-table-keys", Exact(1)),
+    Vector => ("vector", Variadic),
+    MakeVector => ("make-vector", Exact(2)),
+    Aref => ("aref", Exact(2)),
+    Aset => ("aset", Exact(3)),
+    Vconcat => ("vconcat", Variadic),
+    Add => ("+", Variadic),
+    Subtract => ("-", Min(1)),
+    Multiply => ("*", Variadic),
+    Divide => ("/", Min(1)),
+    Mod => ("mod", Exact(2)),
+    Rem => ("rem", Exact(2)),
+    Quotient => ("quotient", Exact(2)),
+    Abs => ("abs", Exact(1)),
+    Minimum => ("min", Min(1)),
+    Maximum => ("max", Min(1)),
+    Expt => ("expt", Exact(2)),
+    Sqrt => ("sqrt", Exact(1)),
+    Floor => ("floor", Range(1, 2)),
+    Ceiling => ("ceiling", Range(1, 2)),
+    Round => ("round", Range(1, 2)),
+    Truncate => ("truncate", Range(1, 2)),
+    Logand => ("logand", Variadic),
+    Logior => ("logior", Variadic),
+    Logxor => ("logxor", Variadic),
+    Lognot => ("lognot", Exact(1)),
+    Ash => ("ash", Exact(2)),
+    Concat => ("concat", Variadic),
+    Substring => ("substring", Range(1, 3)),
+    StringLength => ("string-length", Exact(1)),
+    Upcase => ("upcase", Exact(1)),
+    Downcase => ("downcase", Exact(1)),
+    StringTrim => ("string-trim", Exact(1)),
+    SplitString => ("split-string", Range(1, 3)),
+    StringJoin => ("string-join", Range(1, 2)),
+    StringPrefixP => ("string-prefix-p", Exact(2)),
+    StringSuffixP => ("string-suffix-p", Exact(2)),
+    StringSearch => ("string-search", Range(2, 3)),
+    StringReplace => ("string-replace", Exact(3)),
+    StringToNumber => ("string-to-number", Range(1, 2)),
+    NumberToString => ("number-to-string", Exact(1)),
+    Format => ("format", Min(1)),
+    Less => ("<", Min(1)),
+    Greater => (">", Min(1)),
+    LessEqual => ("<=", Min(1)),
+    GreaterEqual => (">=", Min(1)),
+    NumEqual => ("=", Min(1)),
+    NumNotEqual => ("/=", Exact(2)),
+    Eq => ("eq", Exact(2)),
+    Eql => ("eql", Exact(2)),
+    Equal => ("equal", Exact(2)),
+    And => ("and", Variadic),
+    Or => ("or", Variadic),
+    Not => ("not", Exact(1)),
+    If => ("if", Min(2)),
+    Cond => ("cond", Variadic),
+    When => ("when", Min(1)),
+    Unless => ("unless", Min(1)),
+    Case => ("case", Min(1)),
+    While => ("while", Min(1)),
+    Dotimes => ("dotimes", Min(3)),
+    Dolist => ("dolist", Min(3)),
+    Loop => ("loop", Variadic),
+    Print => ("print", Exact(1)),
+    Prin1 => ("prin1", Exact(1)),
+    Prin1ToString => ("prin1-to-string", Exact(1)),
+    Let => ("let", Min(1)),
+    LetStar => ("let*", Min(1)),
+    Progn => ("progn", Variadic),
+    Return => ("return", Range(0, 1)),
+    Break => ("break", Exact(0)),
+    ActaeonConnect => ("actaeon-create", Exact(2)),
+    ActaeonReceive => ("actaeon-receive", Exact(1)),
+    ActaeonSend => ("actaeon-send", Exact(2)),
 }
 
 impl Func {
     /// Create a new function. The argument comes is the name of the arrow function.
-    pub fn new(name: &str) -> Result<Self, &'static str> {
+    /// Only built-in functions are found, see [Func::resolve] for
+    /// functions defined with `defun`.
+    pub fn new(name: &str) -> Result<Self, ArrowError> {
+        Self::lookup(name).ok_or_else(|| ArrowError::UnknownFunction {
+            name: name.to_string(),
+            span: None,
+        })
+    }
+
+    /// Like [Func::new], but every other valid function name results
+    /// in a call of the function defined with `defun`. That is
+    /// [Func::Call], if a macro with that name was defined or compiled
+    /// on this thread, and [Func::Invoke] otherwise. The name has to
+    /// be the first argument of the call.
+    pub fn resolve(name: &str) -> Result<Self, ArrowError> {
+        Self::new(name).or_else(|e| match LispType::new(&[name.to_string()], true) {
+            Ok(LispType::Symbol(_)) if macros::is_declared(name) => Ok(Func::Call),
+            Ok(LispType::Symbol(_)) => Ok(Func::Invoke),
+            _ => Err(e),
+        })
+    }
+
+    /// Check that `name` can be the name of a function or macro, that
+    /// is defined with `defun`, `defmacro` or from Rust. The names of
+    /// builtin functions and special forms can't be used, calls to
+    /// them always run the builtin function.
+    pub fn check_definable(name: &str) -> Result<(), ArrowError> {
+        if Self::lookup(name).is_some() {
+            return Err(ArrowError::invalid(format!(
+                "`{}` is a builtin function and can't be replaced",
+                name
+            )));
+        }
+        match LispType::new(&[name.to_string()], true) {
+            Ok(LispType::Symbol(_)) => Ok(()),
+            _ => Err(ArrowError::invalid(format!(
+                "`{}` isn't a valid function name",
+                name
+            ))),
+        }
+    }
+
+    /// Special forms get their arguments unevaluated and decide
+    /// themselves which of them are run and when. The arguments of
+    /// every other function are run before the function is called.
+    pub fn is_special(&self) -> bool {
         use Func::*;
-        match name {
-            "defun" => Ok(Defun),
-            "+" => Ok(Add),
-            "-" => Ok(Subtract),
-            "*" => Ok(Multiply),
-            "concat" => Ok(Concat),
-            "equal" => Ok(Equal),
-            "print" => Ok(Print),
-            "let" => Ok(Let),
-            "progn" => Ok(Progn),
-            "return" => Ok(Return),
-            "actaeon-create" => Ok(ActaeonConnect),
-            "actaeon-receive" => Ok(ActaeonReceive),
-            "actaeon-send" => Ok(ActaeonSend),
-            _ => Err("invalid argument."),
+        matches!(
+            self,
+            Defun
+                | Defmacro
+                | Call
+                | Invoke
+                | Quote
+                | Lambda
+                | Function
+                | Defvar
+                | Defparameter
+                | Defconst
+                | Setq
+                | Setf
+                | Push
+                | Pop
+                | And
+                | Or
+                | If
+                | Cond
+                | When
+                | Unless
+                | Case
+                | While
+                | Dotimes
+                | Dolist
+                | Loop
+                | Let
+                | LetStar
+                | Progn
+        )
+    }
+
+    /// Fail with an [ArrowError::Arity], if the function doesn't
+    /// accept `found` arguments.
+    pub fn check_arity(&self, found: usize) -> Result<(), ArrowError> {
+        if self.arity().accepts(found) {
+            Ok(())
+        } else {
+            Err(ArrowError::Arity {
+                name: self.name().to_string(),
+                expected: self.arity(),
+                found,
+                span: None,
+            })
         }
     }
 
     /// Function that returns a function pointer, which implements the function that
     /// the arrow function must perform.
-    pub fn get_fn(
-        &self,
-    ) -> Box<dyn Fn(&mut [LispType], &mut Vec<LispType>) -> Result<LispType, &'static str>> {
+    pub fn get_fn(&self) -> Box<BuiltinFn> {
         use Func::*;
         Box::new(match self {
-            Defun => |a: &mut [LispType], v: &mut Vec<LispType>| {
-                if a[0].to_string(&mut vec![]).unwrap() == v[0].to_string(&mut vec![]).unwrap() {
-                    Ok(LispType::new(
-                        &[a.iter_mut()
-                            .skip(1)
-                            .map(|e| e.run(v).unwrap())
-                            .collect::<Vec<LispType>>()
-                            .last()
-                            .unwrap()
-                            .to_string(v)
-                            .unwrap()],
-                        false,
-                    )?)
-                } else {
-                    Ok(LispType::new(&["'null".to_string()], false).unwrap())
+            Defun => |a: &[LispType], env: &Environment| {
+                let function = definition(a)?;
+                let name = function.name.clone();
+                env.define_function(function);
+                Ok(LispType::Symbol(name))
+            },
+            Defmacro => |a: &[LispType], env: &Environment| {
+                let function = definition(a)?;
+                let name = function.name.clone();
+                env.define_macro(function);
+                Ok(LispType::Symbol(name))
+            },
+            Call => |a: &[LispType], env: &Environment| {
+                let name = arg(a, 0)?.to_string_from_symbol()?;
+                if env.function(&name).is_none() && env.macro_function(&name).is_some() {
+                    // The code wasn't expanded, so the macro is expanded
+                    // now. The second argument is the quoted data of the
+                    // arguments.
+                    let form = list::cons(LispType::Symbol(name), arg(a, 1)?.run(env)?);
+                    return compile(&macros::macroexpand(&form, env)?)?.run(env);
                 }
+                call(&name, rest(a, 2), env)
             },
-            Add => |a: &mut [LispType], v: &mut Vec<LispType>| {
-                Ok(LispType::Number(
-                    a[0].run(v)?.num(v)? + a[1].run(v)?.num(v)?,
-                ))
+            Invoke => |a: &[LispType], env: &Environment| {
+                call(&arg(a, 0)?.to_string_from_symbol()?, rest(a, 1), env)
             },
-            Subtract => |a: &mut [LispType], v: &mut Vec<LispType>| {
-                Ok(LispType::Number(
-                    a[0].run(v)?.num(v)? - a[1].run(v)?.num(v)?,
-                ))
+            Quote => |a: &[LispType], _: &Environment| Ok(arg(a, 0)?.clone()),
+            Macroexpand1 => {
+                |a: &[LispType], env: &Environment| macros::macroexpand_1(arg(a, 0)?, env)
+            }
+            Macroexpand => |a: &[LispType], env: &Environment| macros::macroexpand(arg(a, 0)?, env),
+            Gensym => |a: &[LispType], _: &Environment| {
+                let prefix = a.first().map(LispType::string).transpose()?;
+                Ok(macros::gensym(prefix.unwrap_or("g")))
+            },
+            Lambda => |a: &[LispType], env: &Environment| {
+                let (body, params) = a
+                    .split_last()
+                    .ok_or_else(|| ArrowError::invalid("missing function body"))?;
+                Ok(LispType::Closure(Closure::new(
+                    Parameters::parse(params)?,
+                    body.clone(),
+                    env,
+                )))
+            },
+            Function => |a: &[LispType], env: &Environment| match arg(a, 0)? {
+                LispType::Expression(e) if e.func == Lambda => e.run(env),
+                LispType::Symbol(s) => Ok(LispType::Symbol(s.to_string())),
+                other => Err(ArrowError::type_error("function", other.type_name())),
             },
-            Multiply => |a: &mut [LispType], v: &mut Vec<LispType>| {
-                Ok(LispType::Number(
-                    a[0].run(v)?.num(v)? * a[1].run(v)?.num(v)?,
+            Funcall => |a: &[LispType], env: &Environment| {
+                function::funcall(arg(a, 0)?, rest(a, 1).to_vec(), env)
+            },
+            Apply => |a: &[LispType], env: &Environment| {
+                let (list, head) = a
+                    .split_last()
+                    .ok_or_else(|| ArrowError::invalid("missing argument list"))?;
+                let mut args = rest(head, 1).to_vec();
+                args.extend(list::elements(list)?);
+                function::funcall(arg(a, 0)?, args, env)
+            },
+            Defvar => |a: &[LispType], env: &Environment| {
+                let name = arg(a, 0)?.to_string_from_symbol()?;
+                let root = env.root();
+                if let (Some(value), None) = (a.get(1), root.get(&name)) {
+                    let value = value.run(env)?;
+                    root.define(name.as_str(), value);
+                }
+                Ok(LispType::Symbol(name))
+            },
+            Defparameter => |a: &[LispType], env: &Environment| {
+                let name = arg(a, 0)?.to_string_from_symbol()?;
+                env.root().set(&name, arg(a, 1)?.run(env)?)?;
+                Ok(LispType::Symbol(name))
+            },
+            Defconst => |a: &[LispType], env: &Environment| {
+                let name = arg(a, 0)?.to_string_from_symbol()?;
+                env.define_constant(name.as_str(), arg(a, 1)?.run(env)?);
+                Ok(LispType::Symbol(name))
+            },
+            Setq => |a: &[LispType], env: &Environment| {
+                let mut res = LispType::Bool(false);
+                for pair in pairs(a, "setq")? {
+                    res = arg(pair, 1)?.run(env)?;
+                    env.set(&arg(pair, 0)?.to_string_from_symbol()?, res.clone())?;
+                }
+                Ok(res)
+            },
+            Setf => |a: &[LispType], env: &Environment| {
+                let mut res = LispType::Bool(false);
+                for pair in pairs(a, "setf")? {
+                    res = arg(pair, 1)?.run(env)?;
+                    set_place(arg(pair, 0)?, res.clone(), env)?;
+                }
+                Ok(res)
+            },
+            Cons => |a: &[LispType], _: &Environment| {
+                Ok(list::cons(arg(a, 0)?.clone(), arg(a, 1)?.clone()))
+            },
+            Car => |a: &[LispType], _: &Environment| list::car(arg(a, 0)?),
+            Cdr => |a: &[LispType], _: &Environment| list::cdr(arg(a, 0)?),
+            List => |a: &[LispType], _: &Environment| Ok(list::list(a.to_vec())),
+            Append => |a: &[LispType], _: &Environment| list::append(a),
+            Reverse => |a: &[LispType], _: &Environment| list::reverse(arg(a, 0)?),
+            Length => |a: &[LispType], _: &Environment| {
+                let length = match arg(a, 0)? {
+                    LispType::Vector(v) => v.len(),
+                    LispType::String(s) => s.chars().count(),
+                    other => list::length(other)?,
+                };
+                Ok(LispType::Integer(length as i64))
+            },
+            Nth => |a: &[LispType], _: &Environment| list::nth(arg(a, 0)?.int()?, arg(a, 1)?),
+            Nthcdr => |a: &[LispType], _: &Environment| list::nthcdr(arg(a, 0)?.int()?, arg(a, 1)?),
+            Last => |a: &[LispType], _: &Environment| {
+                let n = a.get(1).map(LispType::int).transpose()?.unwrap_or(1);
+                list::last(arg(a, 0)?, n)
+            },
+            Member => |a: &[LispType], _: &Environment| list::member(arg(a, 0)?, arg(a, 1)?),
+            Assoc => |a: &[LispType], _: &Environment| list::assoc(arg(a, 0)?, arg(a, 1)?),
+            Push => |a: &[LispType], env: &Environment| {
+                let item = arg(a, 0)?.run(env)?;
+                let place = arg(a, 1)?;
+                let res = list::cons(item, place.run(env)?);
+                set_place(place, res.clone(), env)?;
+                Ok(res)
+            },
+            Pop => |a: &[LispType], env: &Environment| {
+                let place = arg(a, 0)?;
+                let list = place.run(env)?;
+                set_place(place, list::cdr(&list)?, env)?;
+                list::car(&list)
+            },
+            MakeHashTable => {
+                |a: &[LispType], _: &Environment| Ok(LispType::HashTable(hashtable::make(a)?))
+            }
+            Gethash => |a: &[LispType], _: &Environment| {
+                let default = a.get(2).cloned().unwrap_or(LispType::Bool(false));
+                Ok(hashtable::table(arg(a, 1)?)?
+                    .get(arg(a, 0)?)
+                    .unwrap_or(default))
+            },
+            Puthash => |a: &[LispType], _: &Environment| {
+                let value = arg(a, 1)?.clone();
+                hashtable::table(arg(a, 2)?)?.put(arg(a, 0)?.clone(), value.clone());
+                Ok(value)
+            },
+            Remhash => |a: &[LispType], _: &Environment| {
+                hashtable::table(arg(a, 1)?)?.remove(arg(a, 0)?);
+                Ok(LispType::Bool(false))
+            },
+            Maphash => |a: &[LispType], env: &Environment| {
+                for (key, value) in hashtable::table(arg(a, 1)?)?.entries() {
+                    function::funcall(arg(a, 0)?, vec![key, value], env)?;
+                }
+                Ok(LispType::Bool(false))
+            },
+            HashTableCount => |a: &[LispType], _: &Environment| {
+                Ok(LispType::Integer(
+                    hashtable::table(arg(a, 0)?)?.count() as i64
                 ))
             },
-            Concat => |a: &mut [LispType], v: &mut Vec<LispType>| {
-                Ok(LispType::String(
-                    a[0].run(v)?
-                        .to_string(v)?
-                        .append(a[1].run(v)?.to_string(v)?),
+            HashTableKeys => |a: &[LispType], _: &Environment| {
+                Ok(list::list(hashtable::table(arg(a, 0)?)?.keys()))
+            },
+            Vector => |a: &[LispType], _: &Environment| {
+                Ok(LispType::Vector(vector::Vector::new(a.to_vec())))
+            },
+            MakeVector => |a: &[LispType], _: &Environment| {
+                vector::make(arg(a, 0)?.int()?, arg(a, 1)?.clone())
+            },
+            Aref => {
+                |a: &[LispType], _: &Environment| vector::vector(arg(a, 0)?)?.get(arg(a, 1)?.int()?)
+            }
+            Aset => |a: &[LispType], _: &Environment| {
+                let value = arg(a, 2)?.clone();
+                vector::vector(arg(a, 0)?)?.set(arg(a, 1)?.int()?, value.clone())?;
+                Ok(value)
+            },
+            Vconcat => |a: &[LispType], _: &Environment| vector::vconcat(a),
+            Add => |a: &[LispType], _: &Environment| Ok(number::add(&numbers(a)?)?.into()),
+            Subtract => {
+                |a: &[LispType], _: &Environment| Ok(number::subtract(&numbers(a)?)?.into())
+            }
+            Multiply => {
+                |a: &[LispType], _: &Environment| Ok(number::multiply(&numbers(a)?)?.into())
+            }
+            Divide => |a: &[LispType], _: &Environment| Ok(number::divide(&numbers(a)?)?.into()),
+            Mod => |a: &[LispType], _: &Environment| {
+                let n = numbers(a)?;
+                Ok(number::modulo(n[0], n[1])?.into())
+            },
+            Rem => |a: &[LispType], _: &Environment| {
+                let n = numbers(a)?;
+                Ok(number::rem(n[0], n[1])?.into())
+            },
+            Quotient => |a: &[LispType], _: &Environment| {
+                let n = numbers(a)?;
+                Ok(number::quotient(n[0], n[1])?.into())
+            },
+            Abs => |a: &[LispType], _: &Environment| Ok(number::abs(numbers(a)?[0])?.into()),
+            Minimum => |a: &[LispType], _: &Environment| Ok(number::min(&numbers(a)?)?.into()),
+            Maximum => |a: &[LispType], _: &Environment| Ok(number::max(&numbers(a)?)?.into()),
+            Expt => |a: &[LispType], _: &Environment| {
+                let n = numbers(a)?;
+                Ok(number::expt(n[0], n[1])?.into())
+            },
+            Sqrt => |a: &[LispType], _: &Environment| Ok(number::sqrt(numbers(a)?[0])?.into()),
+            Floor => |a: &[LispType], _: &Environment| Ok(number::floor(&numbers(a)?)?.into()),
+            Ceiling => |a: &[LispType], _: &Environment| Ok(number::ceiling(&numbers(a)?)?.into()),
+            Round => |a: &[LispType], _: &Environment| Ok(number::round(&numbers(a)?)?.into()),
+            Truncate => {
+                |a: &[LispType], _: &Environment| Ok(number::truncate(&numbers(a)?)?.into())
+            }
+            Logand => |a: &[LispType], _: &Environment| Ok(number::logand(&numbers(a)?)?.into()),
+            Logior => |a: &[LispType], _: &Environment| Ok(number::logior(&numbers(a)?)?.into()),
+            Logxor => |a: &[LispType], _: &Environment| Ok(number::logxor(&numbers(a)?)?.into()),
+            Lognot => |a: &[LispType], _: &Environment| Ok(number::lognot(numbers(a)?[0])?.into()),
+            Ash => |a: &[LispType], _: &Environment| {
+                let n = numbers(a)?;
+                Ok(number::ash(n[0], n[1])?.into())
+            },
+            Concat => |a: &[LispType], _: &Environment| string::concat(a),
+            Substring => |a: &[LispType], _: &Environment| {
+                let from = a.get(1).map(LispType::int).transpose()?.unwrap_or(0);
+                let to = a
+                    .get(2)
+                    .filter(|t| t.truthy())
+                    .map(LispType::int)
+                    .transpose()?;
+                string::substring(arg(a, 0)?, from, to)
+            },
+            StringLength => |a: &[LispType], _: &Environment| string::length(arg(a, 0)?),
+            Upcase => |a: &[LispType], _: &Environment| {
+                Ok(LispType::String(arg(a, 0)?.string()?.to_uppercase()))
+            },
+            Downcase => |a: &[LispType], _: &Environment| {
+                Ok(LispType::String(arg(a, 0)?.string()?.to_lowercase()))
+            },
+            StringTrim => |a: &[LispType], _: &Environment| {
+                Ok(LispType::String(arg(a, 0)?.string()?.trim().to_string()))
+            },
+            SplitString => |a: &[LispType], _: &Environment| {
+                let separator = a.get(1).filter(|s| s.truthy());
+                let omit_empty = a.get(2).is_some_and(LispType::truthy);
+                string::split(arg(a, 0)?, separator, omit_empty)
+            },
+            StringJoin => |a: &[LispType], _: &Environment| string::join(arg(a, 0)?, a.get(1)),
+            StringPrefixP => |a: &[LispType], _: &Environment| {
+                Ok(LispType::Bool(
+                    arg(a, 1)?.string()?.starts_with(arg(a, 0)?.string()?),
                 ))
             },
-            Equal => |a: &mut [LispType], v: &mut Vec<LispType>| {
+            StringSuffixP => |a: &[LispType], _: &Environment| {
                 Ok(LispType::Bool(
-                    a[0].run(v)?.to_string(v)? == a[1].run(v)?.to_string(v)?,
+                    arg(a, 1)?.string()?.ends_with(arg(a, 0)?.string()?),
                 ))
             },
-            Print => |a: &mut [LispType], v: &mut Vec<LispType>| {
-                println!("{}", a[0].run(v)?.to_string(v)?);
-                Ok(LispType::Bool(false))
+            StringSearch => |a: &[LispType], _: &Environment| {
+                let start = a.get(2).map(LispType::int).transpose()?.unwrap_or(0);
+                string::search(arg(a, 0)?, arg(a, 1)?, start)
             },
-            Let => |a: &mut [LispType], v: &mut Vec<LispType>| {
-                v.push(LispType::Atom(
-                    a[0].to_string_from_symbol()?,
-                    Box::new(a[1].run(&mut vec![])?),
-                ));
-                let res = a[1].run(v)?;
-                v.pop();
-                Ok(res)
+            StringReplace => |a: &[LispType], _: &Environment| {
+                string::replace(arg(a, 0)?, arg(a, 1)?, arg(a, 2)?)
             },
-            Progn => |a: &mut [LispType], v: &mut Vec<LispType>| {
-                let mut res = LispType::Bool(false);
-                a.iter_mut().for_each(|e| res = e.run(v).unwrap());
+            StringToNumber => |a: &[LispType], _: &Environment| {
+                let base = a
+                    .get(1)
+                    .filter(|b| b.truthy())
+                    .map(LispType::int)
+                    .transpose()?;
+                string::to_number(arg(a, 0)?, base)
+            },
+            NumberToString => |a: &[LispType], _: &Environment| string::from_number(arg(a, 0)?),
+            Format => |a: &[LispType], _: &Environment| string::format(a),
+            Less => |a: &[LispType], _: &Environment| {
+                Ok(LispType::Bool(number::ordered(
+                    &numbers(a)?,
+                    Ordering::is_lt,
+                )))
+            },
+            Greater => |a: &[LispType], _: &Environment| {
+                Ok(LispType::Bool(number::ordered(
+                    &numbers(a)?,
+                    Ordering::is_gt,
+                )))
+            },
+            LessEqual => |a: &[LispType], _: &Environment| {
+                Ok(LispType::Bool(number::ordered(
+                    &numbers(a)?,
+                    Ordering::is_le,
+                )))
+            },
+            GreaterEqual => |a: &[LispType], _: &Environment| {
+                Ok(LispType::Bool(number::ordered(
+                    &numbers(a)?,
+                    Ordering::is_ge,
+                )))
+            },
+            NumEqual => |a: &[LispType], _: &Environment| {
+                Ok(LispType::Bool(number::ordered(
+                    &numbers(a)?,
+                    Ordering::is_eq,
+                )))
+            },
+            NumNotEqual => |a: &[LispType], _: &Environment| {
+                Ok(LispType::Bool(!number::ordered(
+                    &numbers(a)?,
+                    Ordering::is_eq,
+                )))
+            },
+            Eq => {
+                |a: &[LispType], _: &Environment| Ok(LispType::Bool(arg(a, 0)?.is_eq(arg(a, 1)?)))
+            }
+            Eql => {
+                |a: &[LispType], _: &Environment| Ok(LispType::Bool(arg(a, 0)?.is_eql(arg(a, 1)?)))
+            }
+            Equal => |a: &[LispType], _: &Environment| {
+                Ok(LispType::Bool(arg(a, 0)?.is_equal(arg(a, 1)?)))
+            },
+            And => |a: &[LispType], env: &Environment| {
+                let mut res = LispType::Bool(true);
+                for e in a.iter() {
+                    res = e.run(env)?;
+                    if !res.truthy() {
+                        break;
+                    }
+                }
                 Ok(res)
             },
-            Return => |a: &mut [LispType], v: &mut Vec<LispType>| Ok(a[0].run(v)?.clone()),
-            ActaeonConnect => |a: &mut [LispType], v: &mut Vec<LispType>| {
-                Ok(Actaeon::new(
-                    "127.0.0.1",
-                    &(a[0].run(v)?.to_string(v)?),
-                    4242,
-                    &(a[1].run(v)?.to_string(v)?),
-                ))
+            Or => |a: &[LispType], env: &Environment| {
+                for e in a.iter() {
+                    let res = e.run(env)?;
+                    if res.truthy() {
+                        return Ok(res);
+                    }
+                }
+                Ok(LispType::Bool(false))
+            },
+            Not => |a: &[LispType], _: &Environment| Ok(LispType::Bool(!arg(a, 0)?.truthy())),
+            If => |a: &[LispType], env: &Environment| {
+                if arg(a, 0)?.run(env)?.truthy() {
+                    arg(a, 1)?.run(env)
+                } else {
+                    progn(rest(a, 2), env)
+                }
+            },
+            Cond => |a: &[LispType], env: &Environment| {
+                for clause in a.chunks(2) {
+                    let test = arg(clause, 0)?.run(env)?;
+                    if test.truthy() {
+                        return match arg(clause, 1)? {
+                            LispType::Expression(body) if body.args.is_empty() => Ok(test),
+                            body => body.run(env),
+                        };
+                    }
+                }
+                Ok(LispType::Bool(false))
             },
-            ActaeonReceive => |a: &mut [LispType], v: &mut Vec<LispType>| {
-                if let LispType::Actaeon(mut act) = (&a[0]).clone().run(v)? {
-                    Ok(act.receive())
+            When => |a: &[LispType], env: &Environment| {
+                if arg(a, 0)?.run(env)?.truthy() {
+                    progn(rest(a, 1), env)
                 } else {
-                    Err("This is not an acteon type.")
+                    Ok(LispType::Bool(false))
                 }
             },
-            ActaeonSend => |a: &mut [LispType], v: &mut Vec<LispType>| {
-                if let LispType::Actaeon(mut act) = (&a[0]).clone().run(v)? {
-                    Ok(act.send(&a[1].run(v)?.to_string(v)?))
+            Unless => |a: &[LispType], env: &Environment| {
+                if arg(a, 0)?.run(env)?.truthy() {
+                    Ok(LispType::Bool(false))
                 } else {
-                    Err("This is not an acteon type.")
+                    progn(rest(a, 1), env)
+                }
+            },
+            Case => |a: &[LispType], env: &Environment| {
+                let value = arg(a, 0)?.run(env)?;
+                for clause in rest(a, 1).chunks(2) {
+                    let key = arg(clause, 0)?;
+                    if matches!(key, LispType::Bool(true)) || key.is_eql(&value) {
+                        return arg(clause, 1)?.run(env);
+                    }
+                }
+                Ok(LispType::Bool(false))
+            },
+            While => |a: &[LispType], env: &Environment| {
+                catch_return((|| {
+                    while arg(a, 0)?.run(env)?.truthy() {
+                        progn(rest(a, 1), env)?;
+                    }
+                    Ok(LispType::Bool(false))
+                })())
+            },
+            Dotimes => |a: &[LispType], env: &Environment| {
+                let count = arg(a, 1)?.run(env)?.int()?;
+                each(
+                    a,
+                    env,
+                    (0..count.max(0)).map(LispType::Integer),
+                    LispType::Integer(count),
+                )
+            },
+            Dolist => |a: &[LispType], env: &Environment| {
+                let list = arg(a, 1)?.run(env)?;
+                let items = list::elements(&list)?;
+                each(a, env, items.into_iter(), LispType::Bool(false))
+            },
+            Loop => |a: &[LispType], env: &Environment| {
+                catch_return((|| loop {
+                    progn(a, env)?;
+                })())
+            },
+            Print => |a: &[LispType], _: &Environment| {
+                println!("{}", arg(a, 0)?.to_string()?);
+                Ok(LispType::Bool(false))
+            },
+            Prin1 => |a: &[LispType], _: &Environment| {
+                println!("{}", arg(a, 0)?.to_readable()?);
+                Ok(arg(a, 0)?.clone())
+            },
+            Prin1ToString => {
+                |a: &[LispType], _: &Environment| Ok(LispType::String(arg(a, 0)?.to_readable()?))
+            }
+            Let => |a: &[LispType], env: &Environment| bind(a, env, false),
+            LetStar => |a: &[LispType], env: &Environment| bind(a, env, true),
+            Progn => progn,
+            Return => |a: &[LispType], _: &Environment| {
+                unwind(a.first().cloned().unwrap_or(LispType::Bool(false)))
+            },
+            Break => |_: &[LispType], _: &Environment| unwind(LispType::Bool(false)),
+            ActaeonConnect => |a: &[LispType], _: &Environment| {
+                Ok(LispType::Actaeon(Actaeon::new(
+                    "127.0.0.1",
+                    &(arg(a, 0)?.to_string()?),
+                    4242,
+                    &(arg(a, 1)?.to_string()?),
+                )?))
+            },
+            ActaeonReceive => |a: &[LispType], _: &Environment| match arg(a, 0)? {
+                LispType::Actaeon(act) => Ok(act.receive()),
+                other => Err(ArrowError::type_error("actaeon", other.type_name())),
+            },
+            ActaeonSend => |a: &[LispType], _: &Environment| {
+                let msg = arg(a, 1)?.to_string()?;
+                match arg(a, 0)? {
+                    LispType::Actaeon(act) => Ok(act.send(&msg)),
+                    other => Err(ArrowError::type_error("actaeon", other.type_name())),
                 }
             },
         })
     }
 }
 
+/// Read the name, parameters and body of `defun` and `defmacro`.
+fn definition(a: &[LispType]) -> Result<function::Function, ArrowError> {
+    let (body, head) = a
+        .split_last()
+        .ok_or_else(|| ArrowError::invalid("missing function body"))?;
+    let name = arg(head, 0)?.to_string_from_symbol()?;
+    Func::check_definable(&name)?;
+    Ok(function::Function {
+        name,
+        params: Parameters::parse(rest(head, 1))?,
+        body: function::Body::Code(body.clone()),
+    })
+}
+
+/// Get the argument at `index`, or fail if the function didn't get
+/// enough arguments.
+fn arg(a: &[LispType], index: usize) -> Result<&LispType, ArrowError> {
+    let found = a.len();
+    a.get(index).ok_or_else(|| {
+        ArrowError::invalid(format!(
+            "missing argument {}, only {} were given",
+            index + 1,
+            found
+        ))
+    })
+}
+
+/// All arguments from `index` on. The slice is empty, if there are
+/// not enough arguments.
+fn rest(a: &[LispType], index: usize) -> &[LispType] {
+    a.get(index..).unwrap_or_default()
+}
+
+/// Call the function `name` with the results of the code in `args`.
+fn call(name: &str, args: &[LispType], env: &Environment) -> Result<LispType, ArrowError> {
+    let function = match env.function(name) {
+        Some(function) => function,
+        None if env.macro_function(name).is_some() => {
+            return Err(ArrowError::invalid(format!(
+                "`{}` is a macro, that was defined after the call to it was expanded",
+                name
+            )))
+        }
+        None => {
+            return Err(ArrowError::UnknownFunction {
+                name: name.to_string(),
+                span: None,
+            })
+        }
+    };
+    let args = args.iter().map(|e| e.run(env)).collect::<Result<_, _>>()?;
+    function.call(args, env)
+}
+
+/// Run all arguments in order and return the result of the last one,
+/// or `nil` if there are none.
+fn progn(a: &[LispType], env: &Environment) -> Result<LispType, ArrowError> {
+    let mut res = LispType::Bool(false);
+    for e in a.iter() {
+        res = e.run(env)?;
+    }
+    Ok(res)
+}
+
+thread_local! {
+    /// The value of a `return` or `break`, while it is on its way to
+    /// the loop or function, that catches it.
+    static RETURNING: RefCell<Option<LispType>> = const { RefCell::new(None) };
+}
+
+/// Leave the innermost loop or function with `value`. It is passed
+/// up as an [ArrowError::Return], so it stops every expression on the
+/// way. If nothing catches it, the caller gets the error.
+fn unwind(value: LispType) -> Result<LispType, ArrowError> {
+    RETURNING.with(|r| r.replace(Some(value)));
+    Err(ArrowError::unwind())
+}
+
+/// Turn a `return` or `break` into the result of the loop or
+/// function, that caught it.
+pub(crate) fn catch_return(res: Result<LispType, ArrowError>) -> Result<LispType, ArrowError> {
+    match res {
+        Err(ArrowError::Return(unwind)) => match RETURNING.with(|r| r.take()) {
+            Some(value) => Ok(value),
+            None => Err(ArrowError::Return(unwind)),
+        },
+        res => res,
+    }
+}
+
+/// Forget the value of a `return` or `break`, that nothing caught,
+/// when its error leaves arrow.
+pub(crate) fn uncaught<T>(res: Result<T, ArrowError>) -> Result<T, ArrowError> {
+    if let Err(ArrowError::Return(_)) = res {
+        RETURNING.with(|r| r.take());
+    }
+    res
+}
+
+/// Split the arguments of `setq` and `setf` into pairs of places and
+/// values.
+fn pairs<'a>(
+    a: &'a [LispType],
+    name: &str,
+) -> Result<std::slice::Chunks<'a, LispType>, ArrowError> {
+    if a.len().is_multiple_of(2) {
+        Ok(a.chunks(2))
+    } else {
+        Err(ArrowError::invalid(format!(
+            "`{}` needs pairs of places and values",
+            name
+        )))
+    }
+}
+
+/// Store `value` in the place of `setf`, `push` or `pop`. A place is
+/// a variable, an element of a vector like `(aref v 0)`, a value in a
+/// hash table like `(gethash key table)` or the `car` or `cdr` of a
+/// list, that is itself in a place.
+///
+/// Cons cells can't be changed, so setting the `car` of a list stores
+/// a new cell with the new `car` in the place of the list. Other
+/// references to the old list don't see the change.
+fn set_place(place: &LispType, value: LispType, env: &Environment) -> Result<(), ArrowError> {
+    let e = match place {
+        LispType::Symbol(name) => return env.set(name, value),
+        LispType::Expression(e) => e,
+        other => {
+            return Err(ArrowError::invalid(format!(
+                "{} isn't a place, that `setf` can change",
+                other.to_readable()?
+            )))
+        }
+    };
+    match e.func {
+        Func::Car | Func::Cdr => {
+            let list = arg(&e.args, 0)?;
+            let cell = match list.run(env)? {
+                LispType::Cons(cell) if e.func == Func::Car => list::cons(value, cell.cdr.clone()),
+                LispType::Cons(cell) => list::cons(cell.car.clone(), value),
+                other => return Err(ArrowError::type_error("cons", other.type_name())),
+            };
+            set_place(list, cell, env)
+        }
+        Func::Aref => {
+            let vector = arg(&e.args, 0)?.run(env)?;
+            let index = arg(&e.args, 1)?.run(env)?.int()?;
+            vector::vector(&vector)?.set(index, value)
+        }
+        Func::Gethash => {
+            let key = arg(&e.args, 0)?.run(env)?;
+            let table = arg(&e.args, 1)?.run(env)?;
+            hashtable::table(&table)?.put(key, value);
+            Ok(())
+        }
+        other => Err(ArrowError::invalid(format!(
+            "`{}` isn't a place, that `setf` can change",
+            other.name()
+        ))),
+    }
+}
+
+/// Shared implementation of `let` and `let*`. The arguments are pairs
+/// of variable names and values, followed by the body, if there is an
+/// odd number of arguments. The body is run in a new scope, in which
+/// the variables are defined.
+///
+/// If `sequential` is false, all values are calculated before the
+/// first variable is defined, otherwise every value can already use
+/// the variables before it.
+fn bind(a: &[LispType], env: &Environment, sequential: bool) -> Result<LispType, ArrowError> {
+    let (bindings, body) = a.split_at(a.len() - a.len() % 2);
+    let scope = env.child();
+    for binding in bindings.chunks(2) {
+        let name = arg(binding, 0)?.to_string_from_symbol()?;
+        let value = arg(binding, 1)?.run(if sequential { &scope } else { env })?;
+        scope.define(name, value);
+    }
+    progn(body, &scope)
+}
+
+/// Shared implementation of `dotimes` and `dolist`. The arguments are
+/// the loop variable, the form that produced `items`, the result form
+/// and the body. The body is run once for every item, with the loop
+/// variable bound to it in a new scope. Then the loop variable is
+/// bound to `last` and the result form is run.
+fn each(
+    a: &[LispType],
+    env: &Environment,
+    items: impl Iterator<Item = LispType>,
+    last: LispType,
+) -> Result<LispType, ArrowError> {
+    let name = arg(a, 0)?.to_string_from_symbol()?;
+    let scope = |value| {
+        let scope = env.child();
+        scope.define(name.as_str(), value);
+        scope
+    };
+
+    catch_return((|| {
+        for item in items {
+            progn(rest(a, 3), &scope(item))?;
+        }
+        arg(a, 2)?.run(&scope(last))
+    })())
+}
+
+/// Convert all arguments into numbers.
+fn numbers(a: &[LispType]) -> Result<Vec<Number>, ArrowError> {
+    a.iter().map(LispType::number).collect()
+}
+
 /// Struct that contains all necessary data (except variables)
 /// to execute a LispType.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Expression {
     pub func: Func,
     pub args: Vec<LispType>,
+    pub span: Option<Span>,
 }
 
 impl Expression {
@@ -152,18 +1004,34 @@ impl Expression {
     ///
     /// # Example
     /// ```
+    /// use arrow::environment::Environment;
     /// use arrow::expression::Expression;
     /// use arrow::lisptype::LispType;
     ///
-    /// let data = vec![LispType::Number(2.), LispType::Number(3.)];
-    /// let mut expr = Expression::create("+", data).unwrap();
+    /// let data = vec![LispType::Integer(2), LispType::Integer(3)];
+    /// let expr = Expression::create("+", data).unwrap();
     ///
-    /// assert_eq!(expr.run(&mut vec![]).unwrap().num(&mut vec![]).unwrap(), 5.);
+    /// assert_eq!(expr.run(&Environment::default()).unwrap().int().unwrap(), 5);
     /// ```
-    pub fn create(name: &str, args: Vec<LispType>) -> Result<Self, &'static str> {
+    pub fn create(name: &str, mut args: Vec<LispType>) -> Result<Self, ArrowError> {
+        let func = Func::resolve(name)?;
+        match func {
+            Func::Call => {
+                let data = LispType::Expression(Self {
+                    func: Func::Quote,
+                    args: vec![list::list(args.clone())],
+                    span: None,
+                });
+                args.splice(0..0, vec![LispType::Symbol(name.to_string()), data]);
+            }
+            Func::Invoke => args.insert(0, LispType::Symbol(name.to_string())),
+            _ => {}
+        }
+        func.check_arity(args.len())?;
         Ok(Self {
-            func: Func::new(name)?,
+            func,
             args,
+            span: None,
         })
     }
 
@@ -172,10 +1040,11 @@ impl Expression {
     ///
     /// # Examples
     /// ```
+    /// use arrow::environment::Environment;
     /// use arrow::expression::Expression;
     /// use arrow::lisptype::LispType;
     ///
-    /// let mut expr = Expression::create(
+    /// let expr = Expression::create(
     ///         "+",
     ///         vec![
     ///             LispType::new(&["1".to_string()], false).unwrap(),
@@ -183,13 +1052,33 @@ impl Expression {
     ///         ]
     /// ).unwrap();
     ///
-    /// let res = match expr.run(&mut vec![]).unwrap() {
-    ///     LispType::Number(n) => n,
+    /// let res = match expr.run(&Environment::default()).unwrap() {
+    ///     LispType::Integer(n) => n,
     ///     _ => panic!(""),
     /// };
-    /// assert_eq!(res, 3.);
+    /// assert_eq!(res, 3);
     /// ```
-    pub fn run(&mut self, args: &mut Vec<LispType>) -> Result<LispType, &'static str> {
-        (*self.func.get_fn())(&mut self.args, args)
+    pub fn run(&self, env: &Environment) -> Result<LispType, ArrowError> {
+        // Endless recursion fails, before it overflows the stack.
+        let res = match stack::enter() {
+            Some(_guard) => self.eval(env),
+            None => Err(ArrowError::Depth { span: None }),
+        };
+        res.map_err(|e| e.with_span(self.span))
+    }
+
+    fn eval(&self, env: &Environment) -> Result<LispType, ArrowError> {
+        self.func.check_arity(self.args.len()).and_then(|_| {
+            if self.func.is_special() {
+                (*self.func.get_fn())(&self.args, env)
+            } else {
+                let values = self
+                    .args
+                    .iter()
+                    .map(|e| e.run(env))
+                    .collect::<Result<Vec<_>, _>>()?;
+                (*self.func.get_fn())(&values, env)
+            }
+        })
     }
 }
@@ -0,0 +1,308 @@
+use std::{fmt, rc::Rc};
+
+use crate::environment::Environment;
+use crate::error::ArrowError;
+use crate::expression::{catch_return, Arity, Func};
+use crate::lisptype::LispType;
+use crate::list;
+
+/// The parameter list of a function, like `(a b &optional c &rest d)`.
+///
+/// # Examples
+///
+/// ```
+/// use arrow::expression::Arity;
+/// use arrow::function::Parameters;
+/// use arrow::lisptype::LispType;
+///
+/// let list: Vec<LispType> = ["a", "&optional", "b", "&rest", "c"]
+///     .iter()
+///     .map(|s| LispType::Symbol(s.to_string()))
+///     .collect();
+/// let params = Parameters::parse(&list).unwrap();
+///
+/// assert_eq!(params.required, ["a"]);
+/// assert_eq!(params.optional, ["b"]);
+/// assert_eq!(params.rest.as_deref(), Some("c"));
+/// assert_eq!(params.arity(), Arity::Min(1));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Parameters {
+    pub required: Vec<String>,
+    pub optional: Vec<String>,
+    pub rest: Option<String>,
+}
+
+impl Parameters {
+    /// Read a parameter list. Every parameter has to be a symbol.
+    pub fn parse(list: &[LispType]) -> Result<Self, ArrowError> {
+        let mut res = Self::default();
+        let mut optional = false;
+        let mut names = list.iter();
+        while let Some(param) = names.next() {
+            match param.to_string_from_symbol()?.as_str() {
+                "&optional" if !optional => optional = true,
+                "&rest" => {
+                    let rest = names
+                        .next()
+                        .ok_or_else(|| ArrowError::invalid("`&rest` needs a parameter name"))?;
+                    res.rest = Some(rest.to_string_from_symbol()?);
+                    if names.next().is_some() {
+                        return Err(ArrowError::invalid(
+                            "`&rest` must be followed by exactly one parameter",
+                        ));
+                    }
+                }
+                name if name.starts_with('&') => {
+                    return Err(ArrowError::invalid(format!(
+                        "`{}` can't be used as a parameter",
+                        name
+                    )))
+                }
+                name if optional => res.optional.push(name.to_string()),
+                name => res.required.push(name.to_string()),
+            }
+        }
+        Ok(res)
+    }
+
+    /// How many arguments a function with these parameters accepts.
+    pub fn arity(&self) -> Arity {
+        let min = self.required.len();
+        match (self.optional.len(), &self.rest) {
+            (_, Some(_)) => Arity::Min(min),
+            (0, None) => Arity::Exact(min),
+            (optional, None) => Arity::Range(min, min + optional),
+        }
+    }
+
+    /// Define the parameters as variables in `env`. Optional
+    /// parameters without an argument are `nil`. All arguments left
+    /// over are collected in the rest parameter.
+    fn bind(&self, args: Vec<LispType>, env: &Environment) {
+        let mut args = args.into_iter();
+        for name in self.required.iter().chain(&self.optional) {
+            env.define(name.as_str(), args.next().unwrap_or(LispType::Bool(false)));
+        }
+        if let Some(rest) = &self.rest {
+            env.define(rest.as_str(), list::list(args.collect()));
+        }
+    }
+}
+
+/// A function, that was defined in arrow code with `defun`, or in
+/// Rust with [Arrow::register_native](crate::Arrow::register_native).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Function {
+    pub name: String,
+    /// The parameters of a [Body::Code] function. A native function
+    /// has none, it gets all arguments as a slice.
+    pub params: Parameters,
+    pub body: Body,
+}
+
+/// What runs when a [Function] is called.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Body {
+    /// Arrow code, that runs with the parameters bound as variables.
+    Code(LispType),
+    Native(Native),
+}
+
+/// The signature of a function written in Rust.
+pub type NativeFn = dyn Fn(&[LispType]) -> Result<LispType, ArrowError>;
+
+/// A function written in Rust. It gets the evaluated arguments and
+/// can capture state of the program, that embeds arrow.
+#[derive(Clone)]
+pub struct Native {
+    pub arity: Arity,
+    pub f: Rc<NativeFn>,
+}
+
+impl fmt::Debug for Native {
+    /// The closure itself can't be shown.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Native")
+            .field("arity", &self.arity)
+            .finish()
+    }
+}
+
+impl PartialEq for Native {
+    /// Native functions are only equal, if they share the closure.
+    fn eq(&self, other: &Self) -> bool {
+        self.arity == other.arity && Rc::ptr_eq(&self.f, &other.f)
+    }
+}
+
+impl Function {
+    /// Create a function, that runs the Rust closure `f`. It accepts
+    /// as many arguments as `arity` allows.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use arrow::environment::Environment;
+    /// use arrow::expression::Arity;
+    /// use arrow::function::Function;
+    /// use arrow::lisptype::LispType;
+    ///
+    /// let twice = Function::native("twice", Arity::Exact(1), |args| {
+    ///     Ok(LispType::Integer(args[0].int()? * 2))
+    /// });
+    /// let env = Environment::default();
+    ///
+    /// assert_eq!(twice.call(vec![LispType::Integer(4)], &env).unwrap(), LispType::Integer(8));
+    /// assert!(twice.call(vec![], &env).is_err());
+    /// ```
+    pub fn native(
+        name: impl Into<String>,
+        arity: Arity,
+        f: impl Fn(&[LispType]) -> Result<LispType, ArrowError> + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            params: Parameters::default(),
+            body: Body::Native(Native {
+                arity,
+                f: Rc::new(f),
+            }),
+        }
+    }
+
+    /// How many arguments the function accepts.
+    pub fn arity(&self) -> Arity {
+        match &self.body {
+            Body::Code(_) => self.params.arity(),
+            Body::Native(native) => native.arity,
+        }
+    }
+
+    /// Call the function with arguments, that are already evaluated.
+    /// The body runs in a new scope inside the outermost scope of
+    /// `env`, so it can't see the local variables of the caller.
+    pub fn call(&self, args: Vec<LispType>, env: &Environment) -> Result<LispType, ArrowError> {
+        self.call_in(args, &env.root())
+    }
+
+    /// Run the body in a new scope inside `parent`, in which the
+    /// parameters are bound to `args`.
+    fn call_in(&self, args: Vec<LispType>, parent: &Environment) -> Result<LispType, ArrowError> {
+        let arity = self.arity();
+        if !arity.accepts(args.len()) {
+            return Err(ArrowError::Arity {
+                name: self.name.clone(),
+                expected: arity,
+                found: args.len(),
+                span: None,
+            });
+        }
+
+        match &self.body {
+            Body::Code(body) => {
+                let scope = parent.child();
+                self.params.bind(args, &scope);
+                catch_return(body.run(&scope))
+            }
+            Body::Native(native) => (native.f)(&args),
+        }
+    }
+}
+
+/// An anonymous function created by `lambda`. It keeps the scope it
+/// was created in alive, so its body can still use the variables of
+/// that scope after it was left.
+///
+/// # Examples
+///
+/// ```
+/// use arrow::environment::Environment;
+/// use arrow::lisptype::LispType;
+/// use arrow::tokenize::{ast, create_lisptypes};
+///
+/// let code = "(let ((n 10)) (lambda (x) (+ x n)))";
+/// let env = Environment::default();
+/// let lambda = create_lisptypes(ast(code).unwrap()).unwrap()[0]
+///     .run(&env)
+///     .unwrap();
+///
+/// match lambda {
+///     LispType::Closure(c) => {
+///         assert_eq!(c.call(vec![LispType::Integer(1)]).unwrap(), LispType::Integer(11));
+///     }
+///     _ => panic!("expected a closure"),
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Closure {
+    pub function: Rc<Function>,
+    pub env: Environment,
+}
+
+impl Closure {
+    /// Create a closure, that runs `body` inside of `env`.
+    pub fn new(params: Parameters, body: LispType, env: &Environment) -> Self {
+        Self {
+            function: Rc::new(Function {
+                name: "lambda".to_string(),
+                params,
+                body: Body::Code(body),
+            }),
+            env: env.clone(),
+        }
+    }
+
+    /// Call the closure with arguments, that are already evaluated.
+    pub fn call(&self, args: Vec<LispType>) -> Result<LispType, ArrowError> {
+        self.function.call_in(args, &self.env)
+    }
+}
+
+impl fmt::Debug for Closure {
+    /// The scope is left out, because it can contain the closure
+    /// itself.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Closure")
+            .field("function", &self.function)
+            .finish()
+    }
+}
+
+impl PartialEq for Closure {
+    /// Two closures are only equal, if they were created by the same
+    /// `lambda` in the same scope.
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.function, &other.function) && self.env == other.env
+    }
+}
+
+/// Call `f` with arguments, that are already evaluated, like
+/// `funcall` does. `f` is either a [Closure] or the name of a
+/// function, like `'f` or `#'f`. Functions defined with `defun` or
+/// in Rust are found first, then the builtin functions. Special forms like `if`
+/// can't be called.
+pub fn funcall(
+    f: &LispType,
+    args: Vec<LispType>,
+    env: &Environment,
+) -> Result<LispType, ArrowError> {
+    let name = match f {
+        LispType::Closure(c) => return c.call(args),
+        LispType::Symbol(s) => s.as_str(),
+        other => return Err(ArrowError::type_error("function", other.type_name())),
+    };
+    if let Some(function) = env.function(name) {
+        return function.call(args, env);
+    }
+    match Func::new(name)? {
+        func if func.is_special() => Err(ArrowError::invalid(format!(
+            "`{}` is a special form and can't be called indirectly",
+            name
+        ))),
+        func => {
+            func.check_arity(args.len())?;
+            (*func.get_fn())(&args, env)
+        }
+    }
+}
@@ -0,0 +1,364 @@
+//! Hash tables of arrow, like in Emacs Lisp. A hash table maps keys
+//! to values and compares the keys either with `eq` or with `equal`,
+//! see [Test]. Unlike cons cells, hash tables can be changed, all
+//! copies of a table refer to the same table.
+//!
+//! Hash tables are printed like `#s(hash-table test equal data (a 1))`
+//! and the same syntax can be read again. The entries keep the order,
+//! in which they were added.
+
+use std::{
+    cell::RefCell,
+    collections::{hash_map::DefaultHasher, HashMap},
+    fmt,
+    hash::{Hash, Hasher},
+    rc::Rc,
+};
+
+use crate::error::ArrowError;
+use crate::lisptype::{print_nested, LispType, Printer};
+use crate::list;
+
+/// How the keys of a hash table are compared.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Test {
+    /// Keys are compared with `eq`, so lists are only the same key if
+    /// they are the same object. `eql` means the same in arrow.
+    Eq,
+    /// Keys are compared with `equal`.
+    Equal,
+}
+
+impl Test {
+    /// Read the name of a test, like `'equal`.
+    pub fn parse(name: &LispType) -> Result<Self, ArrowError> {
+        match name.to_string_from_symbol()?.as_str() {
+            "eq" | "eql" => Ok(Self::Eq),
+            "equal" => Ok(Self::Equal),
+            other => Err(ArrowError::invalid(format!(
+                "`{}` isn't a hash table test, use `eq` or `equal`",
+                other
+            ))),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Eq => "eq",
+            Self::Equal => "equal",
+        }
+    }
+
+    fn matches(self, a: &LispType, b: &LispType) -> bool {
+        match self {
+            Self::Eq => a.is_eq(b),
+            Self::Equal => a.is_equal(b),
+        }
+    }
+
+    /// Hash a key, so that keys that match have the same hash.
+    fn hash(self, key: &LispType) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash_into(key, &mut hasher, 0);
+        hasher.finish()
+    }
+
+    fn hash_into(self, key: &LispType, hasher: &mut DefaultHasher, depth: usize) {
+        std::mem::discriminant(key).hash(hasher);
+        match key {
+            LispType::Integer(i) => i.hash(hasher),
+            LispType::Float(f) => f.to_bits().hash(hasher),
+            LispType::String(s) | LispType::Symbol(s) => s.hash(hasher),
+            LispType::Bool(b) => b.hash(hasher),
+            LispType::Cons(cell) if self == Self::Eq => Rc::as_ptr(cell).hash(hasher),
+            // Only the start of long or deep lists is hashed, the rest
+            // is left to the comparison.
+            LispType::Cons(_) if depth < 4 => {
+                for item in list::iter(key).take(16) {
+                    self.hash_into(item, hasher, depth + 1);
+                }
+            }
+            LispType::Vector(v) if self == Self::Equal && depth < 4 => {
+                for item in v.to_vec().iter().take(16) {
+                    self.hash_into(item, hasher, depth + 1);
+                }
+            }
+            LispType::Vector(_) => {}
+            LispType::HashTable(table) => Rc::as_ptr(&table.0).hash(hasher),
+            _ => {}
+        }
+    }
+}
+
+struct Table {
+    test: Test,
+    entries: Vec<(LispType, LispType)>,
+    /// The indices of the entries in `entries` by the hash of the key.
+    index: HashMap<u64, Vec<usize>>,
+}
+
+impl Table {
+    fn find(&self, key: &LispType) -> Option<usize> {
+        self.index
+            .get(&self.test.hash(key))?
+            .iter()
+            .copied()
+            .find(|i| self.test.matches(&self.entries[*i].0, key))
+    }
+}
+
+/// A hash table. Cloning it is cheap, the clone refers to the same
+/// table.
+///
+/// # Examples
+///
+/// ```
+/// use arrow::hashtable::{HashTable, Test};
+/// use arrow::lisptype::LispType;
+/// use arrow::list;
+///
+/// let table = HashTable::new(Test::Equal);
+/// let key = list::list(vec![LispType::Integer(1)]);
+/// table.put(key, LispType::Integer(2));
+///
+/// let same = list::list(vec![LispType::Integer(1)]);
+/// assert_eq!(table.get(&same), Some(LispType::Integer(2)));
+/// assert_eq!(table.count(), 1);
+/// ```
+#[derive(Clone)]
+pub struct HashTable(Rc<RefCell<Table>>);
+
+impl HashTable {
+    pub fn new(test: Test) -> Self {
+        Self(Rc::new(RefCell::new(Table {
+            test,
+            entries: vec![],
+            index: HashMap::new(),
+        })))
+    }
+
+    pub fn test(&self) -> Test {
+        self.0.borrow().test
+    }
+
+    /// The value stored for `key`.
+    pub fn get(&self, key: &LispType) -> Option<LispType> {
+        let table = self.0.borrow();
+        table.find(key).map(|i| table.entries[i].1.clone())
+    }
+
+    /// Store `value` for `key`, replacing the old value of the key.
+    pub fn put(&self, key: LispType, value: LispType) {
+        let mut table = self.0.borrow_mut();
+        match table.find(&key) {
+            Some(i) => table.entries[i].1 = value,
+            None => {
+                let hash = table.test.hash(&key);
+                let i = table.entries.len();
+                table.entries.push((key, value));
+                table.index.entry(hash).or_default().push(i);
+            }
+        }
+    }
+
+    /// Remove `key` from the table. Returns if the key was found.
+    pub fn remove(&self, key: &LispType) -> bool {
+        let mut table = self.0.borrow_mut();
+        let i = match table.find(key) {
+            Some(i) => i,
+            None => return false,
+        };
+        table.entries.remove(i);
+        // The following entries moved, so their indices are updated.
+        for indices in table.index.values_mut() {
+            indices.retain(|j| *j != i);
+            for j in indices.iter_mut().filter(|j| **j > i) {
+                *j -= 1;
+            }
+        }
+        table.index.retain(|_, indices| !indices.is_empty());
+        true
+    }
+
+    /// The number of entries.
+    pub fn count(&self) -> usize {
+        self.0.borrow().entries.len()
+    }
+
+    /// A copy of all entries, so the table can be changed while they
+    /// are processed.
+    pub fn entries(&self) -> Vec<(LispType, LispType)> {
+        self.0.borrow().entries.clone()
+    }
+
+    /// All keys in the order they were added.
+    pub fn keys(&self) -> Vec<LispType> {
+        self.0
+            .borrow()
+            .entries
+            .iter()
+            .map(|e| e.0.clone())
+            .collect()
+    }
+}
+
+impl fmt::Debug for HashTable {
+    /// Only the test and size are shown, because a table can contain
+    /// itself.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HashTable")
+            .field("test", &self.test())
+            .field("count", &self.count())
+            .finish()
+    }
+}
+
+impl PartialEq for HashTable {
+    /// Tables are only equal to themselves, like with `eq`.
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Get the hash table out of a value, or fail with a type error.
+pub fn table(value: &LispType) -> Result<&HashTable, ArrowError> {
+    match value {
+        LispType::HashTable(table) => Ok(table),
+        other => Err(ArrowError::type_error("hash-table", other.type_name())),
+    }
+}
+
+/// Create a hash table from the arguments of `make-hash-table`, like
+/// `:test 'equal`. `:size` is accepted, but has no effect.
+pub fn make(args: &[LispType]) -> Result<HashTable, ArrowError> {
+    let mut test = Test::Eq;
+    for (keyword, value) in options(args)? {
+        match keyword.as_str() {
+            ":test" => test = Test::parse(value)?,
+            ":size" => {
+                value.int()?;
+            }
+            other => {
+                return Err(ArrowError::invalid(format!(
+                    "`{}` isn't an option of `make-hash-table`",
+                    other
+                )))
+            }
+        }
+    }
+    Ok(HashTable::new(test))
+}
+
+/// Split a list of options like `:test 'equal` into their names and
+/// values.
+fn options(args: &[LispType]) -> Result<Vec<(String, &LispType)>, ArrowError> {
+    if !args.len().is_multiple_of(2) {
+        return Err(ArrowError::invalid("every option needs a value"));
+    }
+    args.chunks(2)
+        .map(|pair| Ok((pair[0].to_string_from_symbol()?, &pair[1])))
+        .collect()
+}
+
+/// Read the printed representation of a hash table, without the
+/// leading `#s`, like `(hash-table test equal data (a 1 b 2))`.
+pub fn read(data: &LispType) -> Result<HashTable, ArrowError> {
+    let items = list::elements(data)?;
+    match items.first() {
+        Some(LispType::Symbol(s)) if s == "hash-table" => {}
+        _ => {
+            return Err(ArrowError::invalid(
+                "only hash tables can be read with `#s`, like `#s(hash-table data (a 1))`",
+            ))
+        }
+    }
+
+    let mut test = Test::Eq;
+    let mut data = vec![];
+    for (name, value) in options(&items[1..])? {
+        match name.as_str() {
+            "test" => test = Test::parse(value)?,
+            "data" => data = list::elements(value)?,
+            "size" => {}
+            other => {
+                return Err(ArrowError::invalid(format!(
+                    "`{}` isn't a property of hash tables",
+                    other
+                )))
+            }
+        }
+    }
+    if !data.len().is_multiple_of(2) {
+        return Err(ArrowError::invalid(
+            "the data of a hash table needs a value for every key",
+        ));
+    }
+
+    let table = HashTable::new(test);
+    let mut data = data.into_iter();
+    while let (Some(key), Some(value)) = (data.next(), data.next()) {
+        table.put(key, value);
+    }
+    Ok(table)
+}
+
+/// Print a hash table like `#s(hash-table test equal data (a 1))`.
+/// The keys and values are printed with `print`.
+pub fn to_string(table: &HashTable, print: Printer) -> Result<String, ArrowError> {
+    let data = print_nested(|| {
+        table
+            .entries()
+            .iter()
+            .map(|(k, v)| Ok(format!("{} {}", print(k)?, print(v)?)))
+            .collect::<Result<Vec<_>, ArrowError>>()
+    })?;
+    Ok(format!(
+        "#s(hash-table test {} data ({}))",
+        table.test().name(),
+        data.join(" ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn int(i: i64) -> LispType {
+        LispType::Integer(i)
+    }
+
+    #[test]
+    fn test_remove_keeps_the_index() {
+        let table = HashTable::new(Test::Eq);
+        for i in 0..10 {
+            table.put(int(i), int(i * i));
+        }
+        assert!(table.remove(&int(3)));
+        assert!(!table.remove(&int(3)));
+        assert_eq!(table.count(), 9);
+        for i in (0..10).filter(|i| *i != 3) {
+            assert_eq!(table.get(&int(i)), Some(int(i * i)));
+        }
+        assert_eq!(table.get(&int(3)), None);
+    }
+
+    #[test]
+    fn test_eq_and_equal() {
+        let key = list::list(vec![int(1), int(2)]);
+        let copy = list::list(vec![int(1), int(2)]);
+        for (test, found) in [(Test::Eq, None), (Test::Equal, Some(int(1)))] {
+            let table = HashTable::new(test);
+            table.put(key.clone(), int(1));
+            assert_eq!(table.get(&key), Some(int(1)));
+            assert_eq!(table.get(&copy), found);
+        }
+    }
+
+    #[test]
+    fn test_table_in_itself() {
+        let table = HashTable::new(Test::Eq);
+        table.put(int(1), LispType::HashTable(table.clone()));
+        assert!(to_string(&table, LispType::to_string).is_err());
+        assert_eq!(format!("{:?}", table), "HashTable { test: Eq, count: 1 }");
+    }
+}
@@ -0,0 +1,509 @@
+use std::fmt;
+
+use crate::error::ArrowError;
+use crate::number;
+
+/// A position in the source code. Lines and columns are counted
+/// starting at one, columns are counted in characters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self { line: 1, column: 1 }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// The part of the source code a token (or a whole list) was read
+/// from. `end` points at the last character that belongs to it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    /// Create a span reaching from the start of `self` to the end
+    /// of `other`.
+    pub fn to(self, other: Span) -> Self {
+        Self {
+            start: self.start,
+            end: other.end,
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.start)
+    }
+}
+
+/// All kinds of tokens the lexer knows about.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenKind {
+    OpenParen,
+    CloseParen,
+    OpenBracket,
+    CloseBracket,
+    Quote,
+    /// `#'`, the short form of `(function ...)`.
+    FunctionQuote,
+    Backquote,
+    /// `,` inside of a backquote.
+    Comma,
+    /// `,@` inside of a backquote.
+    CommaAt,
+    /// `#s`, the start of a record like `#s(hash-table data (a 1))`.
+    Record,
+    /// `.` between the last two elements of a dotted list like `(a . b)`.
+    Dot,
+    String,
+    Number,
+    Symbol,
+}
+
+/// A single token. The `text` is the exact text from the source
+/// code, string literals still contain their quotation marks.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub text: String,
+    pub span: Span,
+}
+
+/// Split the source code into [Token]s.
+///
+/// # Examples
+///
+/// ```
+/// use arrow::lexer::{lex, TokenKind};
+///
+/// let tokens = lex("(concat \"hello world\" 'a)").unwrap();
+/// let kinds: Vec<TokenKind> = tokens.iter().map(|t| t.kind).collect();
+///
+/// assert_eq!(
+///     kinds,
+///     vec![
+///         TokenKind::OpenParen,
+///         TokenKind::Symbol,
+///         TokenKind::String,
+///         TokenKind::Quote,
+///         TokenKind::Symbol,
+///         TokenKind::CloseParen,
+///     ]
+/// );
+/// assert_eq!(tokens[2].text, "\"hello world\"");
+/// assert_eq!(tokens[2].span.start.column, 9);
+/// ```
+pub fn lex(code: &str) -> Result<Vec<Token>, ArrowError> {
+    Lexer::new(code).run()
+}
+
+struct Lexer<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    position: Position,
+    last: Position,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(code: &'a str) -> Self {
+        Self {
+            chars: code.chars().peekable(),
+            position: Position::default(),
+            last: Position::default(),
+        }
+    }
+
+    /// Consume the next character and keep track of the position.
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        self.last = self.position;
+        if c == '\n' {
+            self.position.line += 1;
+            self.position.column = 1;
+        } else {
+            self.position.column += 1;
+        }
+        Some(c)
+    }
+
+    fn run(mut self) -> Result<Vec<Token>, ArrowError> {
+        let mut tokens = vec![];
+
+        while let Some(&c) = self.chars.peek() {
+            let start = self.position;
+            let kind = match c {
+                c if c.is_whitespace() => {
+                    self.bump();
+                    continue;
+                }
+                ';' => {
+                    while self.chars.peek().is_some_and(|c| *c != '\n') {
+                        self.bump();
+                    }
+                    continue;
+                }
+                '#' if self.chars.clone().nth(1) == Some('|') => {
+                    self.block_comment(start)?;
+                    continue;
+                }
+                // Printed values like `#<function lambda>` can't be read.
+                '#' if self.chars.clone().nth(1) == Some('<') => {
+                    return Err(ArrowError::parse(
+                        "`#<` starts a value, that can't be read",
+                        Some(Span { start, end: start }),
+                    ))
+                }
+                '(' => TokenKind::OpenParen,
+                ')' => TokenKind::CloseParen,
+                '[' => TokenKind::OpenBracket,
+                ']' => TokenKind::CloseBracket,
+                '\'' => TokenKind::Quote,
+                '`' => TokenKind::Backquote,
+                ',' if self.chars.clone().nth(1) == Some('@') => {
+                    tokens.push(self.prefix(start, TokenKind::CommaAt, ",@"));
+                    continue;
+                }
+                ',' => TokenKind::Comma,
+                '#' if self.chars.clone().nth(1) == Some('\'') => {
+                    tokens.push(self.prefix(start, TokenKind::FunctionQuote, "#'"));
+                    continue;
+                }
+                '#' if self.chars.clone().nth(1) == Some('s')
+                    && self.chars.clone().nth(2) == Some('(') =>
+                {
+                    tokens.push(self.prefix(start, TokenKind::Record, "#s"));
+                    continue;
+                }
+                '"' => {
+                    tokens.push(self.string(start)?);
+                    continue;
+                }
+                _ => {
+                    tokens.push(self.atom(start)?);
+                    continue;
+                }
+            };
+            self.bump();
+            tokens.push(Token {
+                kind,
+                text: c.to_string(),
+                span: Span { start, end: start },
+            });
+        }
+
+        Ok(tokens)
+    }
+
+    /// Skip a comment like `#| ... |#`. Block comments can be nested.
+    fn block_comment(&mut self, start: Position) -> Result<(), ArrowError> {
+        self.bump();
+        self.bump();
+        let mut depth = 1;
+        while depth > 0 {
+            match (self.bump(), self.chars.peek()) {
+                (Some('|'), Some('#')) => depth -= 1,
+                (Some('#'), Some('|')) => depth += 1,
+                (Some(_), _) => continue,
+                (None, _) => {
+                    return Err(ArrowError::parse(
+                        "unterminated block comment",
+                        Some(Span { start, end: start }),
+                    ))
+                }
+            }
+            self.bump();
+        }
+        Ok(())
+    }
+
+    /// Consume a prefix of two characters, like `,@` or `#'`.
+    fn prefix(&mut self, start: Position, kind: TokenKind, text: &str) -> Token {
+        self.bump();
+        self.bump();
+        Token {
+            kind,
+            text: text.to_string(),
+            span: Span {
+                start,
+                end: self.last,
+            },
+        }
+    }
+
+    /// Read a string literal. It can span multiple lines and contain
+    /// escapes, see [unescape]. The token keeps the text from the
+    /// source, with quotation marks and escapes.
+    fn string(&mut self, start: Position) -> Result<Token, ArrowError> {
+        let mut text = String::new();
+        text.extend(self.bump());
+
+        loop {
+            match self.bump() {
+                Some('"') => break,
+                Some('\\') => {
+                    text.push('\\');
+                    text.extend(self.bump());
+                }
+                Some(c) => text.push(c),
+                None => {
+                    return Err(ArrowError::parse(
+                        "unterminated string literal",
+                        Some(Span { start, end: start }),
+                    ))
+                }
+            }
+        }
+        text.push('"');
+
+        let span = Span {
+            start,
+            end: self.last,
+        };
+        unescape(&text).map_err(|e| e.with_span(Some(span)))?;
+        Ok(Token {
+            kind: TokenKind::String,
+            text,
+            span,
+        })
+    }
+
+    fn atom(&mut self, start: Position) -> Result<Token, ArrowError> {
+        let mut text = String::new();
+
+        while let Some(&c) = self.chars.peek() {
+            if c.is_whitespace() || "()[]'`,\";".contains(c) {
+                break;
+            }
+            text.extend(self.bump());
+        }
+
+        let span = Span {
+            start,
+            end: self.last,
+        };
+        let kind = match number::parse(&text) {
+            _ if text == "." => TokenKind::Dot,
+            Ok(Some(_)) => TokenKind::Number,
+            Ok(None) => TokenKind::Symbol,
+            Err(e) => return Err(e.with_span(Some(span))),
+        };
+
+        Ok(Token { kind, text, span })
+    }
+}
+
+/// The value of a string literal like `"a\\tb"`, without its
+/// quotation marks. The escapes `\\n`, `\\t`, `\\"`, `\\\\` and `\\uXXXX`,
+/// with four hexadecimal digits, are replaced by the characters they
+/// stand for.
+///
+/// # Examples
+///
+/// ```
+/// use arrow::lexer::unescape;
+///
+/// assert_eq!(unescape(r#""say \"hi\"\n""#).unwrap(), "say \"hi\"\n");
+/// assert_eq!(unescape(r#""\u00e4""#).unwrap(), "ä");
+/// assert!(unescape(r#""\q""#).is_err());
+/// ```
+pub fn unescape(literal: &str) -> Result<String, ArrowError> {
+    let inner = literal
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .ok_or_else(|| ArrowError::parse("expected a string literal", None))?;
+    let mut res = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            res.push(c);
+            continue;
+        }
+        res.push(match chars.next() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('u') => {
+                let digits: String = chars.by_ref().take(4).collect();
+                u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .filter(|_| digits.len() == 4 && digits.chars().all(|c| c.is_ascii_hexdigit()))
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| {
+                        ArrowError::parse(
+                            format!("`\\u{}` isn't a valid unicode escape", digits),
+                            None,
+                        )
+                    })?
+            }
+            Some(c) => {
+                return Err(ArrowError::parse(
+                    format!("unknown escape `\\{}` in a string", c),
+                    None,
+                ))
+            }
+            None => return Err(ArrowError::parse("unterminated string literal", None)),
+        });
+    }
+    Ok(res)
+}
+
+/// Write a string as a literal, that [unescape] reads back, like
+/// `prin1` in elisp.
+///
+/// # Examples
+///
+/// ```
+/// use arrow::lexer::{escape, unescape};
+///
+/// let s = "a \"b\"\\c\nd";
+///
+/// assert_eq!(escape(s), r#""a \"b\"\\c
+/// d""#);
+/// assert_eq!(unescape(&escape(s)).unwrap(), s);
+/// ```
+pub fn escape(s: &str) -> String {
+    let mut res = String::from('"');
+    for c in s.chars() {
+        if c == '"' || c == '\\' {
+            res.push('\\');
+        }
+        res.push(c);
+    }
+    res.push('"');
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(code: &str) -> Vec<TokenKind> {
+        lex(code).unwrap().iter().map(|t| t.kind).collect()
+    }
+
+    #[test]
+    fn test_lex_kinds() {
+        use TokenKind::*;
+        assert_eq!(
+            kinds("(defun 'f [] (+ 1.5 \"a b\" '(a . b)))"),
+            vec![
+                OpenParen,
+                Symbol,
+                Quote,
+                Symbol,
+                OpenBracket,
+                CloseBracket,
+                OpenParen,
+                Symbol,
+                Number,
+                String,
+                Quote,
+                OpenParen,
+                Symbol,
+                Dot,
+                Symbol,
+                CloseParen,
+                CloseParen,
+                CloseParen
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lex_spans() {
+        let tokens = lex("(+ 12\n  \"x y\")").unwrap();
+        assert_eq!(tokens[2].text, "12");
+        assert_eq!(
+            tokens[2].span,
+            Span {
+                start: Position { line: 1, column: 4 },
+                end: Position { line: 1, column: 5 },
+            }
+        );
+        assert_eq!(tokens[3].text, "\"x y\"");
+        assert_eq!(
+            tokens[3].span,
+            Span {
+                start: Position { line: 2, column: 3 },
+                end: Position { line: 2, column: 7 },
+            }
+        );
+    }
+
+    #[test]
+    fn test_lex_unterminated_string() {
+        assert!(lex("(print \"hello)").is_err());
+        assert!(lex(r#"(print "hello\")"#).is_err());
+    }
+
+    #[test]
+    fn test_lex_strings() {
+        let tokens = lex("(f \"a (b)\nc\" \"\\\"\\\\\")").unwrap();
+        assert_eq!(tokens[2].text, "\"a (b)\nc\"");
+        assert_eq!(tokens[2].span.end, Position { line: 2, column: 2 });
+        assert_eq!(tokens[3].text, r#""\"\\""#);
+        assert_eq!(unescape(&tokens[3].text).unwrap(), r#""\"#);
+        assert_eq!(tokens[4].kind, TokenKind::CloseParen);
+
+        let err = lex("(f \"\\x\")").unwrap_err();
+        assert_eq!(err.span().unwrap().start.column, 4);
+        assert!(lex(r#""\u12""#).is_err());
+        assert!(lex(r#""\ud800""#).is_err());
+    }
+
+    #[test]
+    fn test_lex_function_quote() {
+        use TokenKind::*;
+        assert_eq!(
+            kinds("(funcall #'f #x1F)"),
+            vec![OpenParen, Symbol, FunctionQuote, Symbol, Number, CloseParen]
+        );
+        assert_eq!(lex("#'f").unwrap()[0].span.end.column, 2);
+    }
+
+    #[test]
+    fn test_lex_comments() {
+        use TokenKind::*;
+        assert_eq!(
+            kinds("(a ; comment (\n b;c\n \"; x\")"),
+            vec![OpenParen, Symbol, Symbol, String, CloseParen]
+        );
+        assert_eq!(
+            kinds("#| a #| nested |# ( |# (b #||#) #|\n|#"),
+            vec![OpenParen, Symbol, CloseParen]
+        );
+        let tokens = lex("#| x\n |# a").unwrap();
+        assert_eq!(tokens[0].span.start, Position { line: 2, column: 5 });
+        assert_eq!(kinds("a#|b"), vec![Symbol]);
+        let err = lex("(a) #| #| |#").unwrap_err();
+        assert_eq!(err.span().unwrap().start.column, 5);
+    }
+
+    #[test]
+    fn test_lex_unreadable() {
+        let err = lex("(f #<function lambda>)").unwrap_err();
+        assert_eq!(err.span().unwrap().start.column, 4);
+    }
+
+    #[test]
+    fn test_lex_backquote() {
+        use TokenKind::*;
+        assert_eq!(
+            kinds("`(a ,b ,@c)"),
+            vec![Backquote, OpenParen, Symbol, Comma, Symbol, CommaAt, Symbol, CloseParen]
+        );
+        assert_eq!(kinds("a,b"), vec![Symbol, Comma, Symbol]);
+    }
+}
@@ -8,8 +8,8 @@
 //! a lot of function names come from the dialect Emacs Lisp (elisp).
 //!
 //! ```lisp
-//! (defun greet
-//!     (print "Hello World!"))
+//! (defun greet (name)
+//!     (print (concat "Hello " name)))
 //! ```
 //!
 //! # Examples
@@ -18,9 +18,9 @@
 //! use arrow::Arrow;
 //!
 //! let mut arrow = Arrow::default()
-//!     .add_function("(defun 'calc (+ 2 (* 2 3)))").unwrap();
+//!     .add_function("(defun calc () (+ 2 (* 2 3)))").unwrap();
 //!
-//! assert_eq!(arrow.run("'calc").unwrap().num(&mut vec![]).unwrap(), 8.);
+//! assert_eq!(arrow.run("calc").unwrap().num().unwrap(), 8.);
 //! ```
 //!
 //! # Caveats
@@ -28,70 +28,259 @@
 //! If you are using this library in your own project. You have to
 //! understand, that sometimes, you are using the same functions, that
 //! the library is using internally. This has mostly the effect, that
-//! you often have to pass mutable references to Vectors, because these
-//! are internally used to pass lisp variables.
+//! you have to pass an [Environment](environment::Environment) to
+//! `run`, because it holds the lisp variables.
 //!
 //! ```
+//! use arrow::environment::Environment;
 //! use arrow::lisptype::LispType;
 //!
-//! let mut lisptype = LispType::new(&["12.".to_string()], false).unwrap();
+//! let env = Environment::default();
+//! env.define("x", LispType::Integer(12));
 //!
-//! assert_eq!(lisptype.num(&mut vec![]).unwrap(), 12.);
+//! let lisptype = LispType::new(&["x".to_string()], true).unwrap();
+//!
+//! assert_eq!(lisptype.run(&env).unwrap().num().unwrap(), 12.);
 //! ```
 //!
-//! In this example, you can see, that you have to pass an empty vector
-//! into the `.num()` function. This is Vector is used if the [LispType]
-//! is a `Symbol` and it has to look up, if there is a corresponding
-//! variable, the value has to be used.
+//! In this example, you can see, that `x` is a `Symbol`. Running it
+//! looks up the variable with the same name in the environment.
+//!
+//! Rust values are converted with the traits in [convert], so
+//! functions can be called with Rust arguments and their results read
+//! back into Rust types.
+//!
+//! ```
+//! use arrow::Arrow;
+//!
+//! let mut arrow = Arrow::default()
+//!     .add_function("(defun calc (a b) (list (+ a b) (* a b)))").unwrap();
+//!
+//! let (sum, product): (i64, i64) = arrow.call("calc", (3, 4)).unwrap().into_rust().unwrap();
+//! assert_eq!((sum, product), (7, 12));
+//! ```
 
 pub mod actaeon;
+pub mod convert;
+pub mod environment;
+pub mod error;
 pub mod expression;
+pub mod function;
+pub mod hashtable;
+pub mod lexer;
 pub mod lisptype;
+pub mod list;
+pub mod macros;
+pub mod number;
+mod stack;
 pub mod string;
 #[cfg(test)]
 mod tests;
 pub mod tokenize;
+pub mod vector;
 
+use crate::convert::{IntoArgs, IntoNative};
+use crate::environment::Environment;
+use crate::error::ArrowError;
+use crate::expression::{uncaught, Arity, Func};
+use crate::function::Function;
 use crate::lisptype::LispType;
 use crate::tokenize::create_lisptypes;
 
-/// A wrapper struct for this crate.
-#[derive(Debug)]
+/// A wrapper struct for this crate. It is a registry of functions,
+/// that are looked up by their name, and keeps a global
+/// [Environment], so functions and global variables stay defined
+/// between calls.
+///
+/// Defining a function with a name, that is already registered,
+/// replaces the old function, like `defun` does.
+///
+/// # Examples
+///
+/// ```
+/// use arrow::lisptype::LispType;
+/// use arrow::Arrow;
+///
+/// let mut arrow = Arrow::default()
+///     .add_function("(defvar counter 0)").unwrap()
+///     .add_function("(defun tick () (setq counter (+ counter 1)))").unwrap()
+///     .add_function("(defun add (a b) (list a b (+ a b)))").unwrap();
+///
+/// arrow.run("tick").unwrap();
+/// assert_eq!(arrow.run("tick").unwrap(), LispType::Integer(2));
+/// assert_eq!(arrow.environment().get("counter"), Some(LispType::Integer(2)));
+///
+/// let res = arrow.call("add", &[LispType::Integer(1), LispType::Integer(2)]).unwrap();
+/// assert_eq!(res.to_string().unwrap(), "(1 2 3)");
+/// assert_eq!(arrow.functions(), vec!["add", "tick"]);
+/// ```
+#[derive(Debug, Default)]
 pub struct Arrow {
-    funcs: Vec<LispType>,
+    env: Environment,
 }
 
-impl Default for Arrow {
-    fn default() -> Self {
-        Self { funcs: vec![] }
+impl Arrow {
+    /// Run the definitions in `f` in the global environment, so the
+    /// functions they define are registered. `f` can contain any
+    /// number of forms, but at least one.
+    pub fn add_function(self, f: &str) -> Result<Self, ArrowError> {
+        let forms = create_lisptypes(crate::tokenize::ast(f)?)?;
+        if forms.is_empty() {
+            return Err(ArrowError::parse("expected a function definition", None));
+        }
+        for form in forms {
+            uncaught(crate::macros::expand(form, &self.env)?.run(&self.env))?;
+        }
+        Ok(self)
     }
-}
 
-impl Arrow {
-    /// Add a function to the Crate wrapper struct.
-    pub fn add_function(mut self, f: &str) -> Result<Self, &'static str> {
-        let tokens = crate::tokenize::ast(f);
-        let lisptype = create_lisptypes(vec![tokens[0].clone()])?;
-        self.funcs
-            .push(lisptype.get(0).ok_or("Invalid input")?.clone());
+    /// Register the Rust closure `f` as the function `name`. Arrow
+    /// code calls it like any other function, with as many evaluated
+    /// arguments as `arity` allows. The closure can capture the state
+    /// of the embedding program. Like `defun`, it replaces a function
+    /// with the same name, but builtin functions can't be replaced.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::{cell::Cell, rc::Rc};
+    ///
+    /// use arrow::expression::Arity;
+    /// use arrow::lisptype::LispType;
+    /// use arrow::Arrow;
+    ///
+    /// let hits = Rc::new(Cell::new(0));
+    /// let counter = hits.clone();
+    /// let mut arrow = Arrow::default()
+    ///     .register_native("hit", Arity::Range(0, 1), move |args| {
+    ///         let n = args.first().map(LispType::int).transpose()?.unwrap_or(1);
+    ///         counter.set(counter.get() + n);
+    ///         Ok(LispType::Integer(counter.get()))
+    ///     })
+    ///     .unwrap();
+    ///
+    /// assert_eq!(arrow.eval("(hit) (hit 5)").unwrap(), LispType::Integer(6));
+    /// assert_eq!(arrow.eval("(funcall #'hit)").unwrap(), LispType::Integer(7));
+    /// assert_eq!(hits.get(), 7);
+    ///
+    /// let nil = |_: &[LispType]| Ok(LispType::Bool(false));
+    /// assert!(Arrow::default().register_native("car", Arity::Exact(1), nil).is_err());
+    /// ```
+    pub fn register_native(
+        self,
+        name: &str,
+        arity: Arity,
+        f: impl Fn(&[LispType]) -> Result<LispType, ArrowError> + 'static,
+    ) -> Result<Self, ArrowError> {
+        Func::check_definable(name)?;
+        self.env.define_function(Function::native(name, arity, f));
         Ok(self)
     }
 
+    /// Register a Rust closure with typed parameters as the function
+    /// `name`, like [Arrow::register_native]. The arguments are
+    /// converted with [FromLisp](convert::FromLisp) and the result with
+    /// [IntoLisp](convert::IntoLisp), the arity is the number of
+    /// parameters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use arrow::error::ArrowError;
+    /// use arrow::lisptype::LispType;
+    /// use arrow::Arrow;
+    ///
+    /// let mut arrow = Arrow::default()
+    ///     .register_fn("repeat", |s: String, n: usize| -> Result<_, ArrowError> {
+    ///         Ok(vec![s; n])
+    ///     })
+    ///     .unwrap();
+    ///
+    /// let res = arrow.eval("(repeat \"ab\" 2)").unwrap();
+    /// assert_eq!(res.to_readable().unwrap(), "(\"ab\" \"ab\")");
+    /// assert!(arrow.eval("(repeat 1 2)").is_err());
+    /// ```
+    pub fn register_fn<Args, F>(self, name: &str, f: F) -> Result<Self, ArrowError>
+    where
+        F: IntoNative<Args> + 'static,
+    {
+        self.register_native(name, F::arity(), move |args| f.call(args))
+    }
+
+    /// Call the registered function `name` with `args`. The arguments
+    /// are passed as they are, without evaluating them. A tuple of
+    /// Rust values passes every item as one argument.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use arrow::Arrow;
+    ///
+    /// let mut arrow = Arrow::default()
+    ///     .add_function("(defun calc (a b) (+ a (* 2 b)))").unwrap();
+    ///
+    /// let n: i64 = arrow.call("calc", (1, 2)).unwrap().into_rust().unwrap();
+    /// assert_eq!(n, 5);
+    /// ```
+    pub fn call(&mut self, name: &str, args: impl IntoArgs) -> Result<LispType, ArrowError> {
+        let func = self
+            .env
+            .function(name)
+            .ok_or_else(|| ArrowError::UnknownFunction {
+                name: name.to_string(),
+                span: None,
+            })?;
+        uncaught(func.call(args.into_args(), &self.env))
+    }
+
     /// Execute a function, that is registered in the Arrow struct.
-    pub fn run(&mut self, n: &str) -> Result<LispType, &'static str> {
-        LispType::new(
-            &[self
-                .funcs
-                .iter_mut()
-                .map(|e| {
-                    e.run(&mut vec![LispType::new(&[n.to_string()], false).unwrap()])
-                        .unwrap()
-                })
-                .collect::<Vec<LispType>>()
-                .last()
-                .unwrap()
-                .to_string(&mut vec![])?],
-            false,
-        )
+    /// The function is called without arguments.
+    pub fn run(&mut self, n: &str) -> Result<LispType, ArrowError> {
+        self.call(n, ())
+    }
+
+    /// The names of all registered functions, in alphabetical order.
+    pub fn functions(&self) -> Vec<String> {
+        self.env.function_names()
+    }
+
+    /// Remove the function `name`. Returns if it was registered.
+    pub fn remove_function(&mut self, name: &str) -> bool {
+        self.env.remove_function(name)
+    }
+
+    /// Read every form in `code` and evaluate them in order in the
+    /// global environment. Macros defined by a form can be used by
+    /// the following forms. Returns the value of the last form, or
+    /// `nil` if there is none.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use arrow::lisptype::LispType;
+    /// use arrow::Arrow;
+    ///
+    /// let mut arrow = Arrow::default();
+    /// let code = "
+    ///     ; Square a number.
+    ///     (defun square (x) (* x x))
+    ///     #| Forms run in order,
+    ///        so `square` is already defined. |#
+    ///     (square 7)";
+    ///
+    /// assert_eq!(arrow.eval(code).unwrap(), LispType::Integer(49));
+    /// assert_eq!(arrow.eval("(square 2)").unwrap(), LispType::Integer(4));
+    /// ```
+    pub fn eval(&mut self, code: &str) -> Result<LispType, ArrowError> {
+        let mut res = LispType::Bool(false);
+        for form in create_lisptypes(crate::tokenize::ast(code)?)? {
+            res = uncaught(crate::macros::expand(form, &self.env)?.run(&self.env))?;
+        }
+        Ok(res)
+    }
+
+    /// The global environment, in which all definitions run.
+    pub fn environment(&self) -> &Environment {
+        &self.env
     }
 }
@@ -1,39 +1,73 @@
-use std::panic;
+use std::{collections::HashSet, rc::Rc};
 
-use crate::{actaeon::Actaeon, expression::Expression};
+use crate::{
+    actaeon::Actaeon,
+    environment::Environment,
+    error::ArrowError,
+    expression::Expression,
+    function::Closure,
+    hashtable::{self, HashTable},
+    lexer::{escape, unescape},
+    list::{self, Cons},
+    number::{self, Number},
+    stack,
+    tokenize::MAX_DEPTH,
+    vector::{self, Vector},
+};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum LispType {
-    Number(f64),
+    Integer(i64),
+    Float(f64),
     String(String),
     Bool(bool),
     Expression(Expression),
     Symbol(String),
-    Atom(String, Box<LispType>),
     Actaeon(Actaeon),
+    Closure(Closure),
+    Cons(Rc<Cons>),
+    HashTable(HashTable),
+    Vector(Vector),
 }
 
 impl LispType {
-    pub fn new(args: &[String], flag: bool) -> Result<Self, &'static str> {
+    /// Create a [LispType] from a single token. `'a` is read like
+    /// `(quote a)`. If `flag` is set, other names are symbols,
+    /// otherwise they are calls of functions without arguments.
+    pub fn new(args: &[String], flag: bool) -> Result<Self, ArrowError> {
         if args.len() == 1 {
-            if let Ok(n) = args[0].parse::<f64>() {
-                return Ok(Self::Number(n));
-            } else if args[0].chars().nth(0).unwrap() == '\'' {
-                return Ok(Self::Symbol(args[0].to_string()));
-            } else if args[0].chars().nth(0).unwrap() == '"' || flag {
-                return Ok(Self::String(args[0].to_string()));
+            if let Some(n) = number::parse(&args[0])? {
+                Ok(n.into())
+            } else if args[0].starts_with('\'') {
+                let quoted = args[0].trim_start_matches('\'');
+                let quotes = args[0].len() - quoted.len();
+                if quotes > MAX_DEPTH {
+                    return Err(ArrowError::parse("quotes are nested too deep", None));
+                }
+                let mut res = Self::new(&[quoted.to_string()], true)?;
+                for _ in 0..quotes {
+                    res = Self::Expression(Expression::create("quote", vec![res])?);
+                }
+                Ok(res)
             } else if args[0] == "t" {
-                return Ok(Self::Bool(true));
+                Ok(Self::Bool(true))
             } else if args[0] == "nil" {
-                return Ok(Self::Bool(false));
+                Ok(Self::Bool(false))
+            } else if args[0].starts_with('"') {
+                Ok(Self::String(unescape(&args[0])?))
+            } else if flag {
+                Ok(Self::Symbol(args[0].to_string()))
             } else {
-                return Ok(Self::Expression(Expression::create(
+                Ok(Self::Expression(Expression::create(
                     args[0].as_str(),
                     vec![],
-                )?));
+                )?))
             }
         } else {
-            return Err("Not implemented");
+            Err(ArrowError::invalid(format!(
+                "expected exactly one token, got {}",
+                args.len()
+            )))
         }
         // args.iter().for_each() {
         //     |arg| if arg[1] == ("\"" | "'") {
@@ -42,142 +76,333 @@ impl LispType {
         // }
     }
 
-    /// Run a [LispType]. This function will return a new
-    /// instance of itself (with the same data in it), except
-    /// when it is an Expression. Then it will execute the
-    /// [Expression].
+    /// Run a [LispType]. Expressions are executed and symbols
+    /// evaluate to the value of the variable with their name in
+    /// `env`, unbound symbols are an error. Every other type evaluates
+    /// to itself.
     ///
     /// # Examples
     /// ```
+    /// use arrow::environment::Environment;
     /// use arrow::lisptype::LispType;
     /// use arrow::expression::Expression;
     ///
-    /// let mut lt = LispType::Expression(Expression::create("*", vec![LispType::Number(2.),
-    ///                                                            LispType::Number(2.)]).unwrap());
-    /// assert_eq!(lt.run(&mut vec![]).unwrap().num(&mut vec![]).unwrap(), 4.);
-    /// ```    
-    pub fn run(&mut self, args: &mut Vec<LispType>) -> Result<Self, &'static str> {
+    /// let env = Environment::default();
+    /// env.define("x", LispType::Integer(2));
+    ///
+    /// let lt = LispType::Expression(Expression::create("*", vec![LispType::Symbol("x".to_string()),
+    ///                                                            LispType::Integer(2)]).unwrap());
+    /// assert_eq!(lt.run(&env).unwrap().int().unwrap(), 4);
+    /// ```
+    pub fn run(&self, env: &Environment) -> Result<Self, ArrowError> {
         match self {
-            Self::Expression(e) => (*e).run(args),
-            Self::Number(n) => Ok(Self::Number(*n)),
-            Self::String(s) => Ok(Self::String((*s).clone())),
-            Self::Bool(b) => Ok(Self::Bool(*b)),
-            Self::Symbol(s) => Ok(Self::Symbol((*s).clone())),
-            Self::Atom(_, _) => Err("Cannot return atom!"),
-            Self::Actaeon(_) => unreachable!(),
+            Self::Expression(e) => e.run(env),
+            // Keywords like `:test` evaluate to themselves.
+            Self::Symbol(s) if s.starts_with(':') => Ok(self.clone()),
+            Self::Symbol(s) => match env.get(s) {
+                Some(value) => Ok(value),
+                None => Err(ArrowError::UnboundVariable {
+                    name: s.to_string(),
+                    span: None,
+                }),
+            },
+            _ => Ok(self.clone()),
         }
     }
 
-    /// Shortcut to get the [f64] out of the enum.
-    /// In every case, except if it is an [f64], it
-    /// will fail.
+    /// Shortcut to get the [Number] out of the enum. Every other type
+    /// results in an error.
     ///
     /// # Examples
     /// ```
     /// use arrow::lisptype::LispType;
+    /// use arrow::number::Number;
     ///
-    /// let lt = LispType::Number(1.);
+    /// let lt = LispType::Integer(1);
     ///
-    /// assert_eq!(lt.num(&mut vec![]).unwrap(), 1.);
+    /// assert_eq!(lt.number().unwrap(), Number::Integer(1));
     /// ```
-    pub fn num(&self, vars: &mut Vec<Self>) -> Result<f64, &'static str> {
+    pub fn number(&self) -> Result<Number, ArrowError> {
         match self {
-            Self::Number(n) => Ok(*n),
-            // Self::Bool(b) => match *b {
-            //     true => Ok(1.),
-            //     false => Ok(0.),
-            // },
-            Self::Symbol(s) => {
-                let mut res = 0.;
-                let mut flag = true;
-                vars.iter().for_each(|n| match n {
-                    Self::Atom(a, b) if a == s => {
-                        flag = false;
-                        match **b {
-                            Self::Number(n) => res = n,
-                            _ => panic!("This shouldn't happen."),
-                        };
-                    }
-                    _ => {}
-                });
-                if flag {
-                    return Err("Didn't find variable with that name.");
-                }
-                Ok(res)
-            }
-            _ => Err("Couldn't convert number."),
+            Self::Integer(i) => Ok(Number::Integer(*i)),
+            Self::Float(f) => Ok(Number::Float(*f)),
+            _ => Err(ArrowError::type_error("number", self.type_name())),
+        }
+    }
+
+    /// Shortcut to get an [f64] out of the enum. Integers are
+    /// converted into floats.
+    ///
+    /// # Examples
+    /// ```
+    /// use arrow::lisptype::LispType;
+    ///
+    /// let lt = LispType::Float(1.5);
+    ///
+    /// assert_eq!(lt.num().unwrap(), 1.5);
+    /// assert_eq!(LispType::Integer(2).num().unwrap(), 2.);
+    /// ```
+    pub fn num(&self) -> Result<f64, ArrowError> {
+        Ok(self.number()?.to_f64())
+    }
+
+    /// Shortcut to get the [i64] out of the enum. Floats are not
+    /// converted and result in an error.
+    ///
+    /// # Examples
+    /// ```
+    /// use arrow::lisptype::LispType;
+    ///
+    /// assert_eq!(LispType::Integer(3).int().unwrap(), 3);
+    /// assert!(LispType::Float(3.).int().is_err());
+    /// ```
+    pub fn int(&self) -> Result<i64, ArrowError> {
+        match self.number()? {
+            Number::Integer(i) => Ok(i),
+            Number::Float(_) => Err(ArrowError::type_error("integer", "float")),
         }
     }
 
     /// Shortcut to get the [bool] out of the enum.
-    /// If it isn't the field `LispType::Bool`, it could
-    /// be that the function will panic.
+    /// Strings (`"t"` and `"nil"`) and numbers (`1` and `0`) are
+    /// converted, every other value results in an error.
     ///
     /// # Examples
     /// ```
     /// use arrow::lisptype::LispType;
     ///
-    /// let lt = LispType::Number(1.);
+    /// let lt = LispType::Integer(1);
     /// let lt_2 = LispType::String("nil".to_string());
     ///
     /// assert_eq!(lt.bool().unwrap(), true);
     /// assert_eq!(lt_2.bool().unwrap(), false);
     /// ```
-    pub fn bool(&self) -> Result<bool, &'static str> {
+    pub fn bool(&self) -> Result<bool, ArrowError> {
         match self {
             Self::Bool(b) => Ok(*b),
             Self::String(s) => match s.as_str() {
                 "t" => Ok(true),
                 "nil" => Ok(false),
-                _ => Err("Couldn't convert string to bool."),
-            },
-            Self::Number(n) => match &n.to_string()[..] {
-                "0" => Ok(false),
-                "1" => Ok(true),
-                _ => Err("Number can't be converted."),
+                _ => Err(ArrowError::type_error("bool", self.type_name())),
             },
-            _ => Err("Couldn't convert to bool."),
+            Self::Integer(0) => Ok(false),
+            Self::Integer(1) => Ok(true),
+            Self::Float(f) if *f == 0. => Ok(false),
+            Self::Float(f) if *f == 1. => Ok(true),
+            _ => Err(ArrowError::type_error("bool", self.type_name())),
+        }
+    }
+
+    /// Check if the value counts as true. Like in elisp, everything
+    /// except `nil` is true.
+    pub fn truthy(&self) -> bool {
+        !matches!(self, Self::Bool(false))
+    }
+
+    /// Identity comparison, like `eq` in elisp. Cons cells, vectors,
+    /// hash tables and closures are only `eq` to themselves. Numbers,
+    /// strings, symbols and bools aren't shared objects in arrow, so
+    /// they are compared by type and value. Floats are only `eq` if
+    /// they have the exact same representation, so `(eq 0.0 -0.0)` is
+    /// `nil`.
+    pub fn is_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Integer(a), Self::Integer(b)) => a == b,
+            (Self::Float(a), Self::Float(b)) => a.to_bits() == b.to_bits(),
+            (Self::String(a), Self::String(b)) => a == b,
+            (Self::Bool(a), Self::Bool(b)) => a == b,
+            (Self::Symbol(a), Self::Symbol(b)) => a == b,
+            (Self::Closure(a), Self::Closure(b)) => a == b,
+            (Self::Cons(a), Self::Cons(b)) => Rc::ptr_eq(a, b),
+            (Self::HashTable(a), Self::HashTable(b)) => a == b,
+            (Self::Vector(a), Self::Vector(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    /// Comparison like `eql` in elisp. In elisp, `eql` differs from
+    /// `eq` only for numbers, that aren't the same object. Arrow never
+    /// shares numbers, so `eq` already compares them by value and both
+    /// predicates are the same.
+    pub fn is_eql(&self, other: &Self) -> bool {
+        self.is_eq(other)
+    }
+
+    /// Structural equality, like `equal` in elisp. Values of different
+    /// types are never equal, so `(equal 1 1.0)` and `(equal 1 "1")`
+    /// are both `nil`. Lists and vectors are equal if all their elements
+    /// are.
+    pub fn is_equal(&self, other: &Self) -> bool {
+        // Nested lists and vectors are compared from a list of pending
+        // pairs instead of recursively, so they can be nested
+        // arbitrarily deep. Vectors can contain themselves, so every
+        // pair of vectors is only compared once.
+        let mut pending = vec![];
+        let mut compared = HashSet::new();
+        if !equal_or_pending(self, other, &mut pending) {
+            return false;
+        }
+        while let Some(pair) = pending.pop() {
+            let same = match &pair {
+                (Self::Cons(a), Self::Cons(b)) => {
+                    equal_or_pending(&a.car, &b.car, &mut pending)
+                        && equal_or_pending(&a.cdr, &b.cdr, &mut pending)
+                }
+                (Self::Vector(a), Self::Vector(b))
+                    if a != b && compared.insert((a.address(), b.address())) =>
+                {
+                    let (a, b) = (a.items(), b.items());
+                    a.len() == b.len()
+                        && a.iter()
+                            .zip(b.iter())
+                            .all(|(x, y)| equal_or_pending(x, y, &mut pending))
+                }
+                _ => true,
+            };
+            if !same {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// If the value is a list or a vector, that can contain other
+    /// values.
+    pub(crate) fn is_nested(&self) -> bool {
+        matches!(self, Self::Cons(_) | Self::Vector(_))
+    }
+
+    /// Print the value, like `princ` in elisp. Strings are printed as
+    /// they are, also inside of lists, see [LispType::to_readable]
+    /// for the opposite.
+    pub fn to_string(&self) -> Result<String, ArrowError> {
+        match self {
+            Self::String(s) => Ok(s.to_string()),
+            _ => self.print(Self::to_string),
+        }
+    }
+
+    /// Print the value, so it can be read again, like `prin1` in
+    /// elisp. Strings are printed with quotation marks and escapes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use arrow::lisptype::LispType;
+    ///
+    /// let s = LispType::String("say \"hi\"".to_string());
+    ///
+    /// assert_eq!(s.to_string().unwrap(), "say \"hi\"");
+    /// assert_eq!(s.to_readable().unwrap(), r#""say \"hi\"""#);
+    /// ```
+    pub fn to_readable(&self) -> Result<String, ArrowError> {
+        match self {
+            Self::String(s) => Ok(escape(s)),
+            _ => self.print(Self::to_readable),
         }
     }
 
-    /// Convert a all implemented LispTypes into LispType::String,
-    pub fn to_string(&self, vars: &mut Vec<LispType>) -> Result<String, &'static str> {
+    /// Print everything but strings. The elements of lists, vectors
+    /// and hash tables are printed with `print`.
+    fn print(&self, print: Printer) -> Result<String, ArrowError> {
         match self {
             Self::String(s) => Ok(s.to_string()),
-            Self::Number(n) => Ok(n.to_string()),
+            Self::Integer(i) => Ok(Number::Integer(*i).to_string()),
+            Self::Float(f) => Ok(Number::Float(*f).to_string()),
             Self::Bool(b) => Ok(match b {
                 true => "t",
                 false => "nil",
             }
             .to_string()),
-            Self::Expression(_) => Err("cant convert closure to string."),
-            Self::Symbol(s) => {
-                let mut res = String::new();
-                let mut flag = true;
-                vars.iter().for_each(|n| match n {
-                    Self::Atom(a, b) => {
-                        if a == s {
-                            flag = false;
-                            res = (*b).to_string(&mut vec![]).unwrap();
-                        }
-                    }
-                    _ => {}
-                });
-                if flag {
-                    res = s.to_string();
-                }
-                Ok(res)
-            }
-            Self::Atom(a, b) => Ok(format!("( {} {} )", a, b.to_string(vars)?)),
-            Self::Actaeon(_) => Err("Cannot convert actaeon to string."),
+            Self::Expression(_) => Err(ArrowError::type_error("string", self.type_name())),
+            Self::Symbol(s) => Ok(s.to_string()),
+            Self::Cons(_) => list::to_string(self, print),
+            Self::HashTable(table) => hashtable::to_string(table, print),
+            Self::Vector(v) => vector::to_string(v, print),
+            // Functions and connections can't be read again, so they
+            // are printed like in elisp, with a `#<` that the reader
+            // rejects.
+            Self::Closure(c) => Ok(format!("#<function {}>", c.function.name)),
+            Self::Actaeon(_) => Ok("#<actaeon>".to_string()),
+        }
+    }
+
+    /// The contents of a string. Every other type is an error.
+    pub fn string(&self) -> Result<&str, ArrowError> {
+        match self {
+            Self::String(s) => Ok(s),
+            other => Err(ArrowError::type_error("string", other.type_name())),
         }
     }
 
     /// Convert a LispType::Symbol to a LispType::String
-    pub fn to_string_from_symbol(&self) -> Result<String, &'static str> {
+    pub fn to_string_from_symbol(&self) -> Result<String, ArrowError> {
         match self {
             Self::Symbol(s) => Ok(s.to_string()),
-            _ => Err("This should only be called if LispType is a Symbol."),
+            _ => Err(ArrowError::type_error("symbol", self.type_name())),
         }
     }
+
+    /// The name of the type, as it is used in error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Integer(_) => "integer",
+            Self::Float(_) => "float",
+            Self::String(_) => "string",
+            Self::Bool(_) => "bool",
+            Self::Expression(_) => "expression",
+            Self::Symbol(_) => "symbol",
+            Self::Actaeon(_) => "actaeon",
+            Self::Closure(_) => "function",
+            Self::Cons(_) => "cons",
+            Self::HashTable(_) => "hash-table",
+            Self::Vector(_) => "vector",
+        }
+    }
+}
+
+/// Compare two values with [LispType::is_eql], unless both are lists
+/// or both are vectors. Those are added to `pending` instead, to
+/// compare their elements later.
+fn equal_or_pending(a: &LispType, b: &LispType, pending: &mut Vec<(LispType, LispType)>) -> bool {
+    match (a, b) {
+        (LispType::Cons(_), LispType::Cons(_)) | (LispType::Vector(_), LispType::Vector(_)) => {
+            pending.push((a.clone(), b.clone()));
+            true
+        }
+        _ => a.is_eql(b),
+    }
+}
+
+/// Drop values one after another from a list of pending values. Lists
+/// and vectors, that aren't used anywhere else, move the lists and
+/// vectors they contain to `pending`, so dropping deeply nested values
+/// can't overflow the stack.
+pub(crate) fn drop_nested(mut pending: Vec<LispType>) {
+    while let Some(value) = pending.pop() {
+        match value {
+            LispType::Cons(cell) => {
+                if let Ok(mut cell) = Rc::try_unwrap(cell) {
+                    cell.unlink(&mut pending);
+                }
+            }
+            LispType::Vector(v) => v.unlink(&mut pending),
+            _ => {}
+        }
+    }
+}
+
+/// A function, that prints a value, either [LispType::to_string] or
+/// [LispType::to_readable].
+pub type Printer = fn(&LispType) -> Result<String, ArrowError>;
+
+/// Print the contents of a list, hash table or vector with `print`.
+/// Lists can be nested arbitrarily deep and hash tables and vectors
+/// can even contain themselves, so this gives up with
+/// [ArrowError::Depth], before printing them overflows the stack.
+pub(crate) fn print_nested<T>(
+    print: impl FnOnce() -> Result<T, ArrowError>,
+) -> Result<T, ArrowError> {
+    let _guard = stack::enter().ok_or(ArrowError::Depth { span: None })?;
+    print()
 }
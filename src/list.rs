@@ -0,0 +1,330 @@
+//! Cons cells and the list functions of arrow. Like in Emacs Lisp, a
+//! list is either `nil`, the empty list, or a cons cell whose `cdr`
+//! is a list. A cons cell whose `cdr` is neither is a dotted pair,
+//! like `(1 . 2)`.
+//!
+//! Cons cells can't be changed after they were created, so lists can
+//! be shared without copying them. `setf` on the `car` or `cdr` of a
+//! list stores a new cell in the place of the list instead.
+
+use std::fmt;
+use std::rc::Rc;
+
+use crate::error::ArrowError;
+use crate::lisptype::{drop_nested, print_nested, LispType, Printer};
+use crate::vector;
+
+/// A single cons cell.
+pub struct Cons {
+    pub car: LispType,
+    pub cdr: LispType,
+}
+
+impl Drop for Cons {
+    /// Free the values, that this cell refers to, with
+    /// [drop_nested]. Dropping them recursively would overflow the
+    /// stack for long lists and for lists, that are nested deeply
+    /// through their `car`.
+    fn drop(&mut self) {
+        let mut pending = vec![];
+        self.unlink(&mut pending);
+        drop_nested(pending);
+    }
+}
+
+impl Cons {
+    /// Move the lists and vectors, that the `car` and `cdr` refer to,
+    /// to `pending`.
+    pub(crate) fn unlink(&mut self, pending: &mut Vec<LispType>) {
+        for value in [&mut self.car, &mut self.cdr] {
+            if value.is_nested() {
+                pending.push(std::mem::replace(value, LispType::Bool(false)));
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Cons {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut cells = f.debug_list();
+        let mut cell = self;
+        loop {
+            cells.entry(&cell.car);
+            match &cell.cdr {
+                LispType::Cons(next) => cell = next,
+                LispType::Bool(false) => break,
+                tail => {
+                    cells.entry(&format_args!(". {:?}", tail));
+                    break;
+                }
+            }
+        }
+        cells.finish()
+    }
+}
+
+impl PartialEq for Cons {
+    /// Compare the elements with `==`. The `cdr`s are walked in a
+    /// loop and nested lists are compared from a list of pending
+    /// pairs, so long and deeply nested lists can't overflow the
+    /// stack.
+    fn eq(&self, other: &Self) -> bool {
+        let mut pending = vec![(self, other)];
+        while let Some((a, b)) = pending.pop() {
+            for pair in [(&a.car, &b.car), (&a.cdr, &b.cdr)] {
+                match pair {
+                    (LispType::Cons(x), LispType::Cons(y)) => pending.push((x, y)),
+                    (x, y) => {
+                        if x != y {
+                            return false;
+                        }
+                    }
+                }
+            }
+        }
+        true
+    }
+}
+
+/// Iterator over the elements of a list. It stops at the first `cdr`,
+/// that isn't a cons cell, see [Iter::tail].
+pub struct Iter<'a> {
+    next: &'a LispType,
+}
+
+impl<'a> Iter<'a> {
+    /// What follows the last cons cell: `nil` for proper lists, the
+    /// last element of a dotted pair or the value itself, if it isn't
+    /// a cons cell at all.
+    pub fn tail(&self) -> &'a LispType {
+        self.next
+    }
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a LispType;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next {
+            LispType::Cons(cell) => {
+                self.next = &cell.cdr;
+                Some(&cell.car)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Iterate over the elements of `list`.
+pub fn iter(list: &LispType) -> Iter<'_> {
+    Iter { next: list }
+}
+
+/// Create a new cons cell.
+pub fn cons(car: LispType, cdr: LispType) -> LispType {
+    LispType::Cons(Rc::new(Cons { car, cdr }))
+}
+
+/// Create a proper list from its elements. An empty list is `nil`.
+///
+/// # Examples
+///
+/// ```
+/// use arrow::list;
+/// use arrow::lisptype::LispType;
+///
+/// let l = list::list(vec![LispType::Integer(1), LispType::Integer(2)]);
+///
+/// assert_eq!(l.to_string().unwrap(), "(1 2)");
+/// assert_eq!(list::list(vec![]), LispType::Bool(false));
+/// ```
+pub fn list(items: Vec<LispType>) -> LispType {
+    dotted(items, LispType::Bool(false))
+}
+
+/// Create a list from its elements, whose last `cdr` is `tail`.
+pub fn dotted(items: Vec<LispType>, tail: LispType) -> LispType {
+    items
+        .into_iter()
+        .rev()
+        .fold(tail, |cdr, car| cons(car, cdr))
+}
+
+/// Check that `list` is a list, either `nil` or a cons cell.
+pub fn check(list: &LispType) -> Result<(), ArrowError> {
+    match list {
+        LispType::Bool(false) | LispType::Cons(_) => Ok(()),
+        other => Err(ArrowError::type_error("list", other.type_name())),
+    }
+}
+
+/// The elements of a proper list.
+pub fn elements(list: &LispType) -> Result<Vec<LispType>, ArrowError> {
+    let mut items = iter(list);
+    let res = items.by_ref().cloned().collect();
+    match items.tail() {
+        LispType::Bool(false) => Ok(res),
+        other => Err(ArrowError::type_error("list", other.type_name())),
+    }
+}
+
+/// The first element of a list. `(car nil)` is `nil`.
+pub fn car(list: &LispType) -> Result<LispType, ArrowError> {
+    check(list)?;
+    Ok(iter(list).next().cloned().unwrap_or(LispType::Bool(false)))
+}
+
+/// Everything but the first element of a list. `(cdr nil)` is `nil`.
+pub fn cdr(list: &LispType) -> Result<LispType, ArrowError> {
+    check(list)?;
+    let mut items = iter(list);
+    items.next();
+    Ok(items.tail().clone())
+}
+
+/// Join lists. Only the last list isn't copied, it becomes the tail
+/// of the result and can be any value. The other arguments can also
+/// be vectors, so `(append [1 2] nil)` turns a vector into a list.
+pub fn append(lists: &[LispType]) -> Result<LispType, ArrowError> {
+    match lists.split_last() {
+        Some((tail, head)) => {
+            let mut items = vec![];
+            for list in head {
+                items.extend(vector::sequence(list)?);
+            }
+            Ok(dotted(items, tail.clone()))
+        }
+        None => Ok(LispType::Bool(false)),
+    }
+}
+
+/// A new list with the elements in reverse order.
+pub fn reverse(list: &LispType) -> Result<LispType, ArrowError> {
+    Ok(elements(list)?
+        .into_iter()
+        .fold(LispType::Bool(false), |res, item| cons(item, res)))
+}
+
+/// The number of elements of a proper list.
+pub fn length(list: &LispType) -> Result<usize, ArrowError> {
+    Ok(elements(list)?.len())
+}
+
+/// The list without its first `n` elements. `nil` if the list is
+/// shorter than that.
+pub fn nthcdr(n: i64, list: &LispType) -> Result<LispType, ArrowError> {
+    check(list)?;
+    let mut res = list;
+    for _ in 0..n.max(0) {
+        res = match res {
+            LispType::Cons(cell) => &cell.cdr,
+            LispType::Bool(false) => break,
+            other => return Err(ArrowError::type_error("list", other.type_name())),
+        };
+    }
+    Ok(res.clone())
+}
+
+/// The element at index `n`, or `nil` if the list is too short.
+pub fn nth(n: i64, list: &LispType) -> Result<LispType, ArrowError> {
+    car(&nthcdr(n, list)?)
+}
+
+/// The last `n` cons cells of a list.
+pub fn last(list: &LispType, n: i64) -> Result<LispType, ArrowError> {
+    check(list)?;
+    let cells = iter(list).count() as i64;
+    nthcdr(cells - n.clamp(0, cells), list)
+}
+
+/// The rest of the list, starting at the first element, that is
+/// `equal` to `item`, or `nil`.
+pub fn member(item: &LispType, list: &LispType) -> Result<LispType, ArrowError> {
+    check(list)?;
+    let mut rest = list;
+    while let LispType::Cons(cell) = rest {
+        if cell.car.is_equal(item) {
+            return Ok(rest.clone());
+        }
+        rest = &cell.cdr;
+    }
+    Ok(LispType::Bool(false))
+}
+
+/// The first element of an association list, whose `car` is `equal`
+/// to `key`, or `nil`. Elements, that aren't cons cells, are skipped.
+pub fn assoc(key: &LispType, alist: &LispType) -> Result<LispType, ArrowError> {
+    check(alist)?;
+    Ok(iter(alist)
+        .find(|item| matches!(item, LispType::Cons(cell) if cell.car.is_equal(key)))
+        .cloned()
+        .unwrap_or(LispType::Bool(false)))
+}
+
+/// Print a list like `(1 2 3)`, or `(1 . 2)` for dotted pairs. The
+/// elements are printed with `print`.
+pub fn to_string(list: &LispType, print: Printer) -> Result<String, ArrowError> {
+    let res = print_nested(|| {
+        let mut items = iter(list);
+        let mut res = vec![];
+        for item in items.by_ref() {
+            res.push(print(item)?);
+        }
+        match items.tail() {
+            LispType::Bool(false) => {}
+            tail => {
+                res.push(".".to_string());
+                res.push(print(tail)?);
+            }
+        }
+        Ok(res)
+    })?;
+    Ok(format!("({})", res.join(" ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ints(items: &[i64]) -> LispType {
+        list(items.iter().map(|i| LispType::Integer(*i)).collect())
+    }
+
+    #[test]
+    fn test_to_string() {
+        let print = LispType::to_string;
+        assert_eq!(to_string(&ints(&[1, 2, 3]), print).unwrap(), "(1 2 3)");
+        let pair = cons(LispType::Integer(1), LispType::Integer(2));
+        assert_eq!(to_string(&pair, print).unwrap(), "(1 . 2)");
+        let nested = list(vec![ints(&[1]), LispType::Bool(true)]);
+        assert_eq!(to_string(&nested, print).unwrap(), "((1) t)");
+        let strings = list(vec![LispType::String("a b".to_string())]);
+        assert_eq!(to_string(&strings, print).unwrap(), "(a b)");
+        assert_eq!(
+            to_string(&strings, LispType::to_readable).unwrap(),
+            "(\"a b\")"
+        );
+    }
+
+    #[test]
+    fn test_long_lists() {
+        let long = ints(&(0..200_000).collect::<Vec<_>>());
+        assert_eq!(length(&long).unwrap(), 200_000);
+        assert_eq!(long, long.clone());
+        assert!(long.is_equal(&reverse(&reverse(&long).unwrap()).unwrap()));
+    }
+
+    #[test]
+    fn test_last_and_nthcdr() {
+        let l = ints(&[1, 2, 3]);
+        assert_eq!(last(&l, 1).unwrap(), ints(&[3]));
+        assert_eq!(last(&l, 2).unwrap(), ints(&[2, 3]));
+        assert_eq!(last(&l, 0).unwrap(), LispType::Bool(false));
+        assert_eq!(last(&l, 9).unwrap(), l);
+        assert_eq!(nthcdr(-1, &l).unwrap(), l);
+        assert_eq!(nthcdr(5, &l).unwrap(), LispType::Bool(false));
+        let pair = cons(LispType::Integer(1), LispType::Integer(2));
+        assert!(nthcdr(2, &pair).is_err());
+        assert!(length(&pair).is_err());
+    }
+}
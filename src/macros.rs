@@ -0,0 +1,212 @@
+//! Macros of arrow. A macro is defined with `defmacro` like a
+//! function, but it gets its arguments as unevaluated data and returns
+//! new code, its expansion. The expansion runs in place of the macro
+//! call.
+//!
+//! Macros are expanded after [create_lisptypes](crate::tokenize::create_lisptypes)
+//! and before the code runs, see [expand]. Every other call becomes a
+//! call of the function with that name, so a macro has to be defined
+//! before the code, that uses it, is expanded. A function can't use a
+//! macro, that is defined after the function. Code, that runs without
+//! being expanded, expands its macro calls when they run.
+//!
+//! Only a call, whose name was already used for a macro on the same
+//! thread when the call was compiled, keeps the data of its arguments
+//! to expand them. Every other call is compiled into a plain call of
+//! the function.
+//!
+//! # Hygiene
+//!
+//! Macros aren't hygienic. The expansion is code like any other code,
+//! so a variable it binds can capture a variable of the caller with
+//! the same name. Bind such variables to a symbol created with
+//! `gensym` instead. Its name starts with `#:`, which can't be read
+//! from the source, so it never clashes with the names of the caller.
+//!
+//! ```lisp
+//! (defmacro swap (a b)
+//!   (let ((tmp (gensym)))
+//!     `(let ((,tmp ,a)) (setq ,a ,b) (setq ,b ,tmp))))
+//! ```
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashSet,
+    rc::Rc,
+};
+
+use crate::environment::Environment;
+use crate::error::ArrowError;
+use crate::expression::{Expression, Func};
+use crate::function::Function;
+use crate::lisptype::LispType;
+use crate::list;
+use crate::stack;
+use crate::tokenize::compile;
+
+/// How often a macro call can expand into another macro call, before
+/// the expansion is considered endless.
+const MAX_EXPANSIONS: usize = 64;
+
+thread_local! {
+    /// The number of the next symbol created by [gensym].
+    static GENSYM: Cell<u64> = const { Cell::new(0) };
+
+    /// The names of all macros, that were defined or compiled on this
+    /// thread, see [declare].
+    static NAMES: RefCell<HashSet<String>> = RefCell::new(HashSet::new());
+}
+
+/// Remember that `name` is the name of a macro. Only calls of these
+/// names keep the data of their arguments when they are compiled, in
+/// case they have to be expanded. Every other call is compiled into a
+/// plain call of a function, see [Func::Invoke].
+pub(crate) fn declare(name: &str) {
+    NAMES.with(|names| {
+        if !names.borrow().contains(name) {
+            names.borrow_mut().insert(name.to_string());
+        }
+    });
+}
+
+/// If a call of `name` might be a call of a macro, because `name` was
+/// declared with [declare].
+pub(crate) fn is_declared(name: &str) -> bool {
+    NAMES.with(|names| names.borrow().contains(name))
+}
+
+/// Create a new symbol, that can't be written in the source code.
+///
+/// # Examples
+///
+/// ```
+/// use arrow::macros::gensym;
+///
+/// assert_ne!(gensym("tmp"), gensym("tmp"));
+/// ```
+pub fn gensym(prefix: &str) -> LispType {
+    let n = GENSYM.with(|n| n.replace(n.get() + 1));
+    LispType::Symbol(format!("#:{}{}", prefix, n))
+}
+
+/// A macro and the arguments it was called with.
+type MacroCall = (Rc<Function>, Vec<LispType>);
+
+/// The macro and its arguments, if `form` is a call to a macro.
+fn macro_call(form: &LispType, env: &Environment) -> Result<Option<MacroCall>, ArrowError> {
+    match form {
+        LispType::Cons(cell) => match &cell.car {
+            LispType::Symbol(name) => match env.macro_function(name) {
+                Some(function) => Ok(Some((function, list::elements(&cell.cdr)?))),
+                None => Ok(None),
+            },
+            _ => Ok(None),
+        },
+        _ => Ok(None),
+    }
+}
+
+/// Expand `form` once, if it is a call to a macro. Any other form is
+/// returned as it is.
+pub fn macroexpand_1(form: &LispType, env: &Environment) -> Result<LispType, ArrowError> {
+    match macro_call(form, env)? {
+        Some((function, args)) => function.call(args, env),
+        None => Ok(form.clone()),
+    }
+}
+
+/// Expand `form` until it isn't a call to a macro anymore. The forms
+/// inside of it aren't expanded.
+pub fn macroexpand(form: &LispType, env: &Environment) -> Result<LispType, ArrowError> {
+    let mut form = form.clone();
+    for _ in 0..MAX_EXPANSIONS {
+        match macro_call(&form, env)? {
+            Some((function, args)) => form = function.call(args, env)?,
+            None => return Ok(form),
+        }
+    }
+    Err(ArrowError::invalid("macros are expanded too deep"))
+}
+
+/// Expand all macro calls in compiled code with the macros defined in
+/// `env`. Quoted data isn't touched.
+///
+/// # Examples
+///
+/// ```
+/// use arrow::environment::Environment;
+/// use arrow::lisptype::LispType;
+/// use arrow::macros::expand;
+/// use arrow::tokenize::{ast, create_lisptypes};
+///
+/// let env = Environment::default();
+/// let code = "(defmacro twice (x) `(* 2 ,x)) (twice (twice 3))";
+/// let mut res = LispType::Bool(false);
+/// for lisptype in create_lisptypes(ast(code).unwrap()).unwrap() {
+///     res = expand(lisptype, &env).unwrap().run(&env).unwrap();
+/// }
+///
+/// assert_eq!(res, LispType::Integer(12));
+/// ```
+pub fn expand(code: LispType, env: &Environment) -> Result<LispType, ArrowError> {
+    expand_in(code, env, 0)
+}
+
+/// Expand `code`, that is already nested in `depth` expansions.
+fn expand_in(code: LispType, env: &Environment, depth: usize) -> Result<LispType, ArrowError> {
+    let e = match code {
+        LispType::Expression(e) if e.func != Func::Quote => e,
+        other => return Ok(other),
+    };
+    if depth == MAX_EXPANSIONS {
+        return Err(ArrowError::invalid("macros are expanded too deep").with_span(e.span));
+    }
+    let _guard = stack::enter().ok_or(ArrowError::Depth { span: e.span })?;
+
+    if e.func == Func::Call {
+        if let Some(form) = call_form(&e, env) {
+            let code = compile(&macroexpand(&form, env).map_err(|err| err.with_span(e.span))?)
+                .map_err(|err| err.with_span(e.span))?;
+            let code = match code {
+                LispType::Expression(inner) => LispType::Expression(Expression {
+                    span: e.span,
+                    ..inner
+                }),
+                other => other,
+            };
+            return expand_in(code, env, depth + 1);
+        }
+    }
+
+    // Any other call is a call of a function. Only its name and its
+    // compiled arguments are needed from now on, not their data.
+    let (func, args) = match e.func {
+        Func::Call => {
+            let mut args = e.args.into_iter();
+            let name = args.next();
+            (Func::Invoke, name.into_iter().chain(args.skip(1)).collect())
+        }
+        func => (func, e.args),
+    };
+    let args = args
+        .into_iter()
+        .map(|a| expand_in(a, env, depth))
+        .collect::<Result<_, _>>()?;
+    Ok(LispType::Expression(Expression { func, args, ..e }))
+}
+
+/// The call as data, if it calls a macro. The second argument of a
+/// call quotes the data of its arguments.
+fn call_form(e: &Expression, env: &Environment) -> Option<LispType> {
+    let name = match e.args.first() {
+        Some(LispType::Symbol(name)) if env.macro_function(name).is_some() => name,
+        _ => return None,
+    };
+    match e.args.get(1) {
+        Some(LispType::Expression(data)) if data.func == Func::Quote => Some(list::cons(
+            LispType::Symbol(name.clone()),
+            data.args.first()?.clone(),
+        )),
+        _ => None,
+    }
+}
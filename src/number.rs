@@ -0,0 +1,590 @@
+//! The numeric functions of arrow. They follow the semantics of
+//! Emacs Lisp: as soon as one argument is a [Number::Float], the
+//! result is a float as well, otherwise integers stay integers and
+//! overflows are reported as errors. `-` with a single argument
+//! negates it and `/` with a single argument returns the reciprocal.
+
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+
+use crate::error::ArrowError;
+use crate::lisptype::LispType;
+
+/// A number, as it is used by the numeric functions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Number {
+    Integer(i64),
+    Float(f64),
+}
+
+impl Number {
+    /// Convert the number into a float, even if it is an integer.
+    pub fn to_f64(self) -> f64 {
+        match self {
+            Self::Integer(i) => i as f64,
+            Self::Float(f) => f,
+        }
+    }
+
+    fn is_zero(self) -> bool {
+        match self {
+            Self::Integer(i) => i == 0,
+            Self::Float(f) => f == 0.,
+        }
+    }
+}
+
+impl From<Number> for LispType {
+    fn from(n: Number) -> Self {
+        match n {
+            Number::Integer(i) => LispType::Integer(i),
+            Number::Float(f) => LispType::Float(f),
+        }
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Integer(i) => write!(f, "{}", i),
+            Self::Float(n) if n.is_nan() => write!(f, "0.0e+NaN"),
+            Self::Float(n) if n.is_infinite() && n > 0. => write!(f, "1.0e+INF"),
+            Self::Float(n) if n.is_infinite() => write!(f, "-1.0e+INF"),
+            // The debug representation always contains a `.` or an
+            // exponent, so floats can be told apart from integers.
+            Self::Float(n) => write!(f, "{:?}", n),
+        }
+    }
+}
+
+/// Parse a number literal. Integers (`12`, `-3`, `12.`), floats
+/// (`1.5`, `1e3`) and integers in hexadecimal (`#x1F`), octal
+/// (`#o17`) and binary (`#b101`) notation are supported. If the text
+/// isn't a number, `None` is returned. Integers that don't fit into
+/// an [i64] are an error.
+///
+/// # Examples
+///
+/// ```
+/// use arrow::number::{parse, Number};
+///
+/// assert_eq!(parse("12").unwrap(), Some(Number::Integer(12)));
+/// assert_eq!(parse("12.0").unwrap(), Some(Number::Float(12.)));
+/// assert_eq!(parse("#x1F").unwrap(), Some(Number::Integer(31)));
+/// assert_eq!(parse("#b-101").unwrap(), Some(Number::Integer(-5)));
+/// assert_eq!(parse("inf").unwrap(), None);
+/// assert!(parse("99999999999999999999").is_err());
+/// ```
+pub fn parse(text: &str) -> Result<Option<Number>, ArrowError> {
+    let too_large = || {
+        ArrowError::parse(
+            format!("the integer {} doesn't fit into 64 bits", text),
+            None,
+        )
+    };
+
+    if let Some(rest) = text.strip_prefix('#') {
+        let mut chars = rest.chars();
+        let radix = match chars.next().map(|c| c.to_ascii_lowercase()) {
+            Some('x') => 16,
+            Some('o') => 8,
+            Some('b') => 2,
+            _ => return Ok(None),
+        };
+        let digits = chars.as_str();
+        let (sign, digits) = match digits.strip_prefix('-') {
+            Some(d) => ("-", d),
+            None => ("", digits.strip_prefix('+').unwrap_or(digits)),
+        };
+        if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+            return Ok(None);
+        }
+        return i64::from_str_radix(&format!("{}{}", sign, digits), radix)
+            .map(|i| Some(Number::Integer(i)))
+            .map_err(|_| too_large());
+    }
+
+    let unsigned = text.strip_prefix(['+', '-']).unwrap_or(text);
+    if !unsigned.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+        || !unsigned
+            .chars()
+            .all(|c| c.is_ascii_digit() || "+-.eE".contains(c))
+        || !unsigned.contains(|c: char| c.is_ascii_digit())
+    {
+        return Ok(None);
+    }
+
+    let integer = unsigned.strip_suffix('.').unwrap_or(unsigned);
+    if integer.chars().all(|c| c.is_ascii_digit()) {
+        text.strip_suffix('.')
+            .unwrap_or(text)
+            .parse::<i64>()
+            .map(|i| Some(Number::Integer(i)))
+            .map_err(|_| too_large())
+    } else {
+        Ok(text.parse::<f64>().ok().map(Number::Float))
+    }
+}
+
+fn division_by_zero() -> ArrowError {
+    ArrowError::Arithmetic {
+        message: "division by zero".to_string(),
+        span: None,
+    }
+}
+
+fn overflow() -> ArrowError {
+    ArrowError::Arithmetic {
+        message: "integer overflow".to_string(),
+        span: None,
+    }
+}
+
+fn check_divisor(d: Number) -> Result<Number, ArrowError> {
+    if d.is_zero() {
+        Err(division_by_zero())
+    } else {
+        Ok(d)
+    }
+}
+
+/// Combine all numbers from left to right. Integer results that
+/// don't fit into an [i64] are an error. If any of the numbers is a
+/// float, everything is calculated with floats.
+fn fold(
+    n: &[Number],
+    init: Number,
+    int: fn(i64, i64) -> Option<i64>,
+    float: fn(f64, f64) -> f64,
+) -> Result<Number, ArrowError> {
+    let ints: Option<Vec<i64>> = n
+        .iter()
+        .map(|x| match x {
+            Number::Integer(i) => Some(*i),
+            Number::Float(_) => None,
+        })
+        .collect();
+    match (ints.as_deref(), n) {
+        (_, []) => Ok(init),
+        (Some([first, rest @ ..]), _) => rest
+            .iter()
+            .try_fold(*first, |acc, x| int(acc, *x).ok_or_else(overflow))
+            .map(Number::Integer),
+        (_, [first, rest @ ..]) => Ok(Number::Float(
+            rest.iter()
+                .fold(first.to_f64(), |acc, x| float(acc, x.to_f64())),
+        )),
+    }
+}
+
+/// Sum of all numbers, `0` if there are none.
+pub fn add(n: &[Number]) -> Result<Number, ArrowError> {
+    fold(n, Number::Integer(0), i64::checked_add, |a, b| a + b)
+}
+
+/// Subtract all numbers from the first one.
+pub fn subtract(n: &[Number]) -> Result<Number, ArrowError> {
+    match n {
+        [x] => subtract(&[Number::Integer(0), *x]),
+        _ => fold(n, Number::Integer(0), i64::checked_sub, |a, b| a - b),
+    }
+}
+
+/// Product of all numbers, `1` if there are none.
+pub fn multiply(n: &[Number]) -> Result<Number, ArrowError> {
+    fold(n, Number::Integer(1), i64::checked_mul, |a, b| a * b)
+}
+
+/// Divide the first number by all other numbers. Integer division
+/// truncates towards zero.
+///
+/// # Examples
+///
+/// ```
+/// use arrow::number::{divide, Number::*};
+///
+/// assert_eq!(divide(&[Integer(12), Integer(2), Integer(3)]).unwrap(), Integer(2));
+/// assert_eq!(divide(&[Integer(7), Integer(2)]).unwrap(), Integer(3));
+/// assert_eq!(divide(&[Integer(7), Float(2.)]).unwrap(), Float(3.5));
+/// assert!(divide(&[Integer(1), Integer(0)]).is_err());
+/// ```
+pub fn divide(n: &[Number]) -> Result<Number, ArrowError> {
+    match n {
+        [x] => divide(&[Number::Integer(1), *x]),
+        [_, rest @ ..] => {
+            for d in rest {
+                check_divisor(*d)?;
+            }
+            fold(n, Number::Integer(1), i64::checked_div, |a, b| a / b)
+        }
+        [] => Ok(Number::Integer(1)),
+    }
+}
+
+/// Remainder of a floored division, the result has the sign of the
+/// divisor.
+pub fn modulo(a: Number, b: Number) -> Result<Number, ArrowError> {
+    let r = rem(a, b)?;
+    if !r.is_zero() && (r.to_f64() < 0.) != (b.to_f64() < 0.) {
+        add(&[r, b])
+    } else {
+        Ok(r)
+    }
+}
+
+/// Remainder of a truncated division, the result has the sign of the
+/// dividend.
+pub fn rem(a: Number, b: Number) -> Result<Number, ArrowError> {
+    fold(&[a, check_divisor(b)?], a, i64::checked_rem, |a, b| a % b)
+}
+
+/// Divide and truncate the result towards zero.
+pub fn quotient(a: Number, b: Number) -> Result<Number, ArrowError> {
+    truncate(&[a, b])
+}
+
+/// Absolute value of the number.
+pub fn abs(n: Number) -> Result<Number, ArrowError> {
+    match n {
+        Number::Integer(i) => i.checked_abs().map(Number::Integer).ok_or_else(overflow),
+        Number::Float(f) => Ok(Number::Float(f.abs())),
+    }
+}
+
+/// Smallest of the numbers. If any of them is a float, the result
+/// is a float.
+pub fn min(n: &[Number]) -> Result<Number, ArrowError> {
+    fold(n, Number::Integer(0), |a, b| Some(a.min(b)), f64::min)
+}
+
+/// Largest of the numbers. If any of them is a float, the result is
+/// a float.
+pub fn max(n: &[Number]) -> Result<Number, ArrowError> {
+    fold(n, Number::Integer(0), |a, b| Some(a.max(b)), f64::max)
+}
+
+/// Raise `base` to the `power`. The result is an integer, if both
+/// are integers and the power isn't negative. A negative power of 0
+/// divides by zero.
+pub fn expt(base: Number, power: Number) -> Result<Number, ArrowError> {
+    match (base, power) {
+        _ if base.is_zero() && power.to_f64() < 0. => Err(division_by_zero()),
+        (Number::Integer(b), Number::Integer(p)) if p >= 0 => u32::try_from(p)
+            .ok()
+            .and_then(|p| b.checked_pow(p))
+            .map(Number::Integer)
+            .ok_or_else(overflow),
+        _ => Ok(Number::Float(base.to_f64().powf(power.to_f64()))),
+    }
+}
+
+/// Square root, negative numbers are rejected. The result is always
+/// a float.
+pub fn sqrt(n: Number) -> Result<Number, ArrowError> {
+    let f = n.to_f64();
+    if f < 0. {
+        Err(ArrowError::invalid(format!(
+            "can't take the square root of {}",
+            n
+        )))
+    } else {
+        Ok(Number::Float(f.sqrt()))
+    }
+}
+
+/// Convert a float into an integer, if it fits.
+fn to_integer(f: f64) -> Result<Number, ArrowError> {
+    if f.is_finite() && f >= i64::MIN as f64 && f < i64::MAX as f64 {
+        Ok(Number::Integer(f as i64))
+    } else {
+        Err(ArrowError::Arithmetic {
+            message: format!("{} can't be converted into an integer", Number::Float(f)),
+            span: None,
+        })
+    }
+}
+
+/// Round `n[0]` (divided by `n[1]` if it is given) to an integer.
+/// `float` rounds floats, `int` does an integer division with the
+/// same rounding. This implements `floor`, `ceiling`, `round` and
+/// `truncate`.
+fn round_with(
+    n: &[Number],
+    float: fn(f64) -> f64,
+    int: fn(i64, i64) -> i64,
+) -> Result<Number, ArrowError> {
+    match n {
+        [Number::Integer(i)] => Ok(Number::Integer(*i)),
+        [x] => to_integer(float(x.to_f64())),
+        [Number::Integer(a), Number::Integer(b)] => {
+            check_divisor(Number::Integer(*b))?;
+            if *a == i64::MIN && *b == -1 {
+                return Err(overflow());
+            }
+            Ok(Number::Integer(int(*a, *b)))
+        }
+        [x, d] => to_integer(float(x.to_f64() / check_divisor(*d)?.to_f64())),
+        _ => Err(ArrowError::invalid(
+            "expected a number and an optional divisor",
+        )),
+    }
+}
+
+/// Round towards negative infinity.
+pub fn floor(n: &[Number]) -> Result<Number, ArrowError> {
+    round_with(n, f64::floor, |a, b| {
+        let q = a / b;
+        q - i64::from(a % b != 0 && (a < 0) != (b < 0))
+    })
+}
+
+/// Round towards positive infinity.
+pub fn ceiling(n: &[Number]) -> Result<Number, ArrowError> {
+    round_with(n, f64::ceil, |a, b| {
+        let q = a / b;
+        q + i64::from(a % b != 0 && (a < 0) == (b < 0))
+    })
+}
+
+/// Round to the nearest integer, ties are rounded to the even one.
+pub fn round(n: &[Number]) -> Result<Number, ArrowError> {
+    round_with(n, f64::round_ties_even, |a, b| {
+        let (q, r) = (a / b, a % b);
+        let twice = (r as i128 * 2).abs();
+        let away = i64::from((a < 0) == (b < 0)) * 2 - 1;
+        if twice > (b as i128).abs() || (twice == (b as i128).abs() && q % 2 != 0) {
+            q + away
+        } else {
+            q
+        }
+    })
+}
+
+/// Round towards zero.
+pub fn truncate(n: &[Number]) -> Result<Number, ArrowError> {
+    round_with(n, f64::trunc, |a, b| a / b)
+}
+
+/// Compare two numbers. Integers are compared exactly, if one of the
+/// numbers is NaN, they can't be compared at all.
+pub fn compare(a: Number, b: Number) -> Option<Ordering> {
+    match (a, b) {
+        (Number::Integer(x), Number::Integer(y)) => Some(x.cmp(&y)),
+        _ => a.to_f64().partial_cmp(&b.to_f64()),
+    }
+}
+
+/// Check if every number and its successor are ordered like `pred`
+/// requires. This implements `<`, `>`, `<=`, `>=` and `=`.
+///
+/// # Examples
+///
+/// ```
+/// use arrow::number::{ordered, Number::*};
+///
+/// assert!(ordered(&[Integer(1), Float(1.5), Integer(2)], |o| o.is_lt()));
+/// assert!(!ordered(&[Integer(1), Integer(1)], |o| o.is_lt()));
+/// ```
+pub fn ordered(n: &[Number], pred: fn(Ordering) -> bool) -> bool {
+    n.windows(2).all(|w| compare(w[0], w[1]).is_some_and(pred))
+}
+
+/// Check that all numbers are integers.
+fn integers(n: &[Number]) -> Result<Vec<i64>, ArrowError> {
+    n.iter()
+        .map(|x| match x {
+            Number::Integer(i) => Ok(*i),
+            Number::Float(_) => Err(ArrowError::type_error("integer", "float")),
+        })
+        .collect()
+}
+
+/// Bitwise and of all integers, `-1` if there are none.
+pub fn logand(n: &[Number]) -> Result<Number, ArrowError> {
+    Ok(Number::Integer(integers(n)?.iter().fold(-1, |a, b| a & b)))
+}
+
+/// Bitwise or of all integers, `0` if there are none.
+pub fn logior(n: &[Number]) -> Result<Number, ArrowError> {
+    Ok(Number::Integer(integers(n)?.iter().fold(0, |a, b| a | b)))
+}
+
+/// Bitwise exclusive or of all integers, `0` if there are none.
+pub fn logxor(n: &[Number]) -> Result<Number, ArrowError> {
+    Ok(Number::Integer(integers(n)?.iter().fold(0, |a, b| a ^ b)))
+}
+
+/// Bitwise complement of an integer.
+pub fn lognot(n: Number) -> Result<Number, ArrowError> {
+    Ok(Number::Integer(!integers(&[n])?[0]))
+}
+
+/// Arithmetic shift: shift `value` to the left by `count` bits, or
+/// to the right if `count` is negative. Shifting to the left fails,
+/// if a bit would be lost or the sign would change. Shifting to the
+/// right by 64 bits or more gives 0 or -1.
+pub fn ash(value: Number, count: Number) -> Result<Number, ArrowError> {
+    let n = integers(&[value, count])?;
+    let (value, count) = (n[0], n[1]);
+    if count >= 0 {
+        let shifted = match value {
+            0 => Some(0),
+            _ if count >= 64 => None,
+            _ => Some(value << count).filter(|s| s >> count == value),
+        };
+        Ok(Number::Integer(shifted.ok_or_else(overflow)?))
+    } else {
+        Ok(Number::Integer(value >> count.unsigned_abs().min(63)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Number::*;
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let parse = |text| parse(text).unwrap();
+        assert_eq!(parse("-3"), Some(Integer(-3)));
+        assert_eq!(parse("12."), Some(Integer(12)));
+        assert_eq!(parse(".5"), Some(Float(0.5)));
+        assert_eq!(parse("1e3"), Some(Float(1000.)));
+        assert_eq!(parse("#o17"), Some(Integer(15)));
+        assert_eq!(parse("#X1f"), Some(Integer(31)));
+        for text in ["-", "+", ".", "e", "1x", "#x", "#xg", "#q1", "nan", "1-"] {
+            assert_eq!(parse(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_parse_too_large() {
+        assert!(super::parse("9223372036854775808").is_err());
+        assert!(super::parse("#x8000000000000000").is_err());
+        assert_eq!(
+            super::parse("-9223372036854775808").unwrap(),
+            Some(Integer(i64::MIN))
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Integer(2).to_string(), "2");
+        assert_eq!(Float(2.).to_string(), "2.0");
+        assert_eq!(Float(0.5).to_string(), "0.5");
+        assert_eq!(Float(f64::INFINITY).to_string(), "1.0e+INF");
+    }
+
+    #[test]
+    fn test_contagion() {
+        assert_eq!(add(&[Integer(1), Integer(2)]).unwrap(), Integer(3));
+        assert_eq!(add(&[Integer(1), Float(2.)]).unwrap(), Float(3.));
+        assert_eq!(
+            divide(&[Integer(5), Integer(2), Float(2.)]).unwrap(),
+            Float(1.25)
+        );
+        assert_eq!(subtract(&[Integer(3)]).unwrap(), Integer(-3));
+        assert_eq!(divide(&[Integer(4)]).unwrap(), Integer(0));
+        assert_eq!(divide(&[Float(4.)]).unwrap(), Float(0.25));
+        assert_eq!(min(&[Integer(1), Float(2.)]).unwrap(), Float(1.));
+    }
+
+    #[test]
+    fn test_overflow() {
+        assert!(multiply(&[Integer(99999999999), Integer(99999999999)]).is_err());
+        assert!(add(&[Integer(i64::MAX), Integer(1)]).is_err());
+        assert!(abs(Integer(i64::MIN)).is_err());
+        assert!(divide(&[Integer(i64::MIN), Integer(-1)]).is_err());
+        assert!(expt(Integer(2), Integer(64)).is_err());
+        assert!(ash(Integer(1), Integer(63)).is_err());
+        assert!(truncate(&[Float(1e300)]).is_err());
+    }
+
+    #[test]
+    fn test_modulo_and_rem() {
+        assert_eq!(modulo(Integer(-7), Integer(2)).unwrap(), Integer(1));
+        assert_eq!(rem(Integer(-7), Integer(2)).unwrap(), Integer(-1));
+        assert_eq!(modulo(Integer(7), Integer(-2)).unwrap(), Integer(-1));
+        assert_eq!(modulo(Float(-7.), Integer(2)).unwrap(), Float(1.));
+        assert_eq!(quotient(Integer(-7), Integer(2)).unwrap(), Integer(-3));
+        assert!(modulo(Integer(1), Integer(0)).is_err());
+    }
+
+    #[test]
+    fn test_expt_of_zero() {
+        assert_eq!(expt(Integer(0), Integer(0)).unwrap(), Integer(1));
+        assert_eq!(expt(Float(0.), Float(0.5)).unwrap(), Float(0.));
+        for (base, power) in [
+            (Integer(0), Integer(-1)),
+            (Float(0.), Integer(-2)),
+            (Float(-0.), Float(-0.5)),
+        ] {
+            assert!(matches!(
+                expt(base, power),
+                Err(ArrowError::Arithmetic { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_rounding() {
+        assert_eq!(round(&[Float(2.5)]).unwrap(), Integer(2));
+        assert_eq!(round(&[Float(-2.5)]).unwrap(), Integer(-2));
+        assert_eq!(round(&[Integer(5), Integer(2)]).unwrap(), Integer(2));
+        assert_eq!(round(&[Integer(7), Integer(2)]).unwrap(), Integer(4));
+        assert_eq!(round(&[Integer(-7), Integer(2)]).unwrap(), Integer(-4));
+        assert_eq!(round(&[Integer(8), Integer(3)]).unwrap(), Integer(3));
+        assert_eq!(floor(&[Integer(7), Integer(2)]).unwrap(), Integer(3));
+        assert_eq!(floor(&[Integer(-7), Integer(2)]).unwrap(), Integer(-4));
+        assert_eq!(floor(&[Integer(7), Integer(-2)]).unwrap(), Integer(-4));
+        assert_eq!(ceiling(&[Integer(7), Integer(2)]).unwrap(), Integer(4));
+        assert_eq!(ceiling(&[Integer(-7), Integer(2)]).unwrap(), Integer(-3));
+        assert_eq!(ceiling(&[Float(-7.), Integer(2)]).unwrap(), Integer(-3));
+        assert_eq!(truncate(&[Float(-2.7)]).unwrap(), Integer(-2));
+        assert!(truncate(&[Integer(7), Integer(0)]).is_err());
+    }
+
+    #[test]
+    fn test_compare() {
+        assert_eq!(compare(Integer(1), Float(1.)), Some(Ordering::Equal));
+        assert_eq!(
+            compare(Integer(i64::MAX), Integer(i64::MAX - 1)),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(compare(Float(f64::NAN), Integer(1)), None);
+        assert!(!ordered(
+            &[Float(f64::NAN), Float(f64::NAN)],
+            Ordering::is_eq
+        ));
+        assert!(ordered(&[Integer(3)], Ordering::is_lt));
+    }
+
+    #[test]
+    fn test_bitwise() {
+        assert_eq!(logand(&[Integer(12), Integer(10)]).unwrap(), Integer(8));
+        assert_eq!(logior(&[Integer(12), Integer(10)]).unwrap(), Integer(14));
+        assert_eq!(logxor(&[Integer(12), Integer(10)]).unwrap(), Integer(6));
+        assert_eq!(lognot(Integer(0)).unwrap(), Integer(-1));
+        assert_eq!(ash(Integer(1), Integer(4)).unwrap(), Integer(16));
+        assert_eq!(ash(Integer(-16), Integer(-2)).unwrap(), Integer(-4));
+        assert!(logand(&[Float(1.)]).is_err());
+    }
+
+    #[test]
+    fn test_ash_limits() {
+        let ash = |value, count| ash(Integer(value), Integer(count));
+        assert_eq!(ash(0, 64).unwrap(), Integer(0));
+        assert_eq!(ash(0, i64::MAX).unwrap(), Integer(0));
+        assert_eq!(ash(1, 62).unwrap(), Integer(1 << 62));
+        assert_eq!(ash(-1, 63).unwrap(), Integer(i64::MIN));
+        assert!(ash(1, 63).is_err());
+        assert!(ash(1, 64).is_err());
+        assert!(ash(-1, 64).is_err());
+        assert!(ash(3, 62).is_err());
+        assert_eq!(ash(i64::MAX, -63).unwrap(), Integer(0));
+        assert_eq!(ash(i64::MAX, -64).unwrap(), Integer(0));
+        assert_eq!(ash(-1, -64).unwrap(), Integer(-1));
+        assert_eq!(ash(i64::MIN, i64::MIN).unwrap(), Integer(-1));
+    }
+}
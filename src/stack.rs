@@ -0,0 +1,60 @@
+//! Protection against overflowing the stack. Reading, compiling,
+//! expanding and running code are recursive, so deeply nested code
+//! needs a lot of stack. How much every level needs depends on the
+//! build, a debug build needs many times more than a release build.
+//! So instead of counting the levels, arrow measures how much stack
+//! it is using on the current thread, and gives up before the stack
+//! ends.
+
+use std::cell::Cell;
+
+/// How much stack arrow can use on one thread. Threads spawned by
+/// Rust get 2 MiB by default, so this leaves enough room for the code
+/// around arrow and for the frames between two checks.
+const STACK_BUDGET: usize = 1024 * 1024;
+
+thread_local! {
+    /// The position of the stack, when arrow was entered on this
+    /// thread.
+    static BASE: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// Marks that arrow is running a recursive step. The first guard on a
+/// thread remembers where the stack started, so it has to be kept
+/// alive until the step is done.
+pub(crate) struct Guard {
+    outermost: bool,
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        if self.outermost {
+            BASE.with(|b| b.set(None));
+        }
+    }
+}
+
+/// The current position of the stack.
+#[inline(never)]
+fn position() -> usize {
+    let marker = 0u8;
+    std::hint::black_box(&marker) as *const u8 as usize
+}
+
+/// Enter a recursive step. Returns [None], if arrow used up its stack
+/// and the step must fail instead.
+pub(crate) fn enter() -> Option<Guard> {
+    let here = position();
+    let (base, outermost) = BASE.with(|b| match b.get() {
+        Some(base) => (base, false),
+        None => {
+            b.set(Some(here));
+            (here, true)
+        }
+    });
+    let guard = Guard { outermost };
+    match base.abs_diff(here) < STACK_BUDGET {
+        true => Some(guard),
+        false => None,
+    }
+}
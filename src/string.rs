@@ -1,3 +1,12 @@
+//! The string functions of arrow. They work on characters, not on
+//! bytes, so indices and lengths count unicode characters like in
+//! Emacs Lisp.
+
+use crate::error::ArrowError;
+use crate::lisptype::LispType;
+use crate::list;
+use crate::number::{self, Number};
+
 pub trait Append {
     fn append(self, s: String) -> String;
 }
@@ -10,14 +19,316 @@ impl Append for String {
     }
 }
 
+/// Join strings, like `concat` in elisp. `nil` counts as an empty
+/// string.
+pub fn concat(args: &[LispType]) -> Result<LispType, ArrowError> {
+    let mut res = String::new();
+    for a in args {
+        if a.truthy() {
+            res = res.append(a.string()?.to_string());
+        }
+    }
+    Ok(LispType::String(res))
+}
+
+/// Turn an index, that can count from the end if it is negative, into
+/// an index from the start.
+fn index(i: i64, length: usize) -> Result<usize, ArrowError> {
+    let res = if i < 0 { length as i64 + i } else { i };
+    if (0..=length as i64).contains(&res) {
+        Ok(res as usize)
+    } else {
+        Err(ArrowError::invalid(format!(
+            "index {} is out of range for a string of length {}",
+            i, length
+        )))
+    }
+}
+
+/// The characters from `from` up to `to`. Negative indices count from
+/// the end of the string, `to` defaults to the end.
+///
+/// # Examples
+///
+/// ```
+/// use arrow::lisptype::LispType;
+/// use arrow::string::substring;
+///
+/// let s = LispType::String("héllo".to_string());
+/// let res = substring(&s, 1, Some(-1)).unwrap();
+///
+/// assert_eq!(res, LispType::String("éll".to_string()));
+/// ```
+pub fn substring(s: &LispType, from: i64, to: Option<i64>) -> Result<LispType, ArrowError> {
+    let chars: Vec<char> = s.string()?.chars().collect();
+    let from = index(from, chars.len())?;
+    let to = index(to.unwrap_or(chars.len() as i64), chars.len())?;
+    if from > to {
+        return Err(ArrowError::invalid(format!(
+            "the start {} of a substring is after its end {}",
+            from, to
+        )));
+    }
+    Ok(LispType::String(chars[from..to].iter().collect()))
+}
+
+/// The number of characters.
+pub fn length(s: &LispType) -> Result<LispType, ArrowError> {
+    Ok(LispType::Integer(s.string()?.chars().count() as i64))
+}
+
+/// Split a string at every occurrence of `separator`. Without a
+/// separator the string is split at whitespace and empty parts are
+/// left out, like in elisp.
+pub fn split(
+    s: &LispType,
+    separator: Option<&LispType>,
+    omit_empty: bool,
+) -> Result<LispType, ArrowError> {
+    let s = s.string()?;
+    let parts: Vec<&str> = match separator {
+        None => s
+            .split(char::is_whitespace)
+            .filter(|p| !p.is_empty())
+            .collect(),
+        Some(separator) => match separator.string()? {
+            "" => return Err(ArrowError::invalid("the separator can't be empty")),
+            separator => s
+                .split(separator)
+                .filter(|p| !omit_empty || !p.is_empty())
+                .collect(),
+        },
+    };
+    Ok(list::list(
+        parts
+            .into_iter()
+            .map(|p| LispType::String(p.to_string()))
+            .collect(),
+    ))
+}
+
+/// Join a list of strings, with `separator` between them.
+pub fn join(strings: &LispType, separator: Option<&LispType>) -> Result<LispType, ArrowError> {
+    let separator = separator.map(LispType::string).transpose()?.unwrap_or("");
+    let strings = list::elements(strings)?;
+    let parts = strings
+        .iter()
+        .map(LispType::string)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(LispType::String(parts.join(separator)))
+}
+
+/// The character index of the first occurrence of `needle` in
+/// `haystack`, starting at the character `start`, or `nil`.
+pub fn search(needle: &LispType, haystack: &LispType, start: i64) -> Result<LispType, ArrowError> {
+    let (needle, haystack) = (needle.string()?, haystack.string()?);
+    if start < 0 {
+        return Err(ArrowError::invalid(
+            "the start of a search can't be negative",
+        ));
+    }
+    let start = index(start, haystack.chars().count())?;
+    let offset = haystack
+        .char_indices()
+        .nth(start)
+        .map_or(haystack.len(), |(i, _)| i);
+    Ok(match haystack[offset..].find(needle) {
+        Some(i) => LispType::Integer((start + haystack[offset..offset + i].chars().count()) as i64),
+        None => LispType::Bool(false),
+    })
+}
+
+/// Replace every occurrence of `from` in `s` with `to`.
+pub fn replace(from: &LispType, to: &LispType, s: &LispType) -> Result<LispType, ArrowError> {
+    match from.string()? {
+        "" => Err(ArrowError::invalid("the string to replace can't be empty")),
+        from => Ok(LispType::String(s.string()?.replace(from, to.string()?))),
+    }
+}
+
+/// Read the number at the start of a string, like `string-to-number`
+/// in elisp. Leading whitespace is skipped and if there is no number,
+/// the result is 0. With a `base`, only integers are read.
+///
+/// # Examples
+///
+/// ```
+/// use arrow::lisptype::LispType;
+/// use arrow::string::to_number;
+///
+/// let s = |s: &str| LispType::String(s.to_string());
+///
+/// assert_eq!(to_number(&s(" 12abc"), None).unwrap(), LispType::Integer(12));
+/// assert_eq!(to_number(&s("1.5e2"), None).unwrap(), LispType::Float(150.));
+/// assert_eq!(to_number(&s("ff"), Some(16)).unwrap(), LispType::Integer(255));
+/// assert_eq!(to_number(&s("abc"), None).unwrap(), LispType::Integer(0));
+/// ```
+pub fn to_number(s: &LispType, base: Option<i64>) -> Result<LispType, ArrowError> {
+    let s = s.string()?.trim_start();
+    let base = match base {
+        // `#x1F` is only a number in the source code.
+        None | Some(10) if s.starts_with('#') => return Ok(LispType::Integer(0)),
+        None | Some(10) => {
+            // The longest prefix, that is a number.
+            let s = &s[..s
+                .find(|c: char| !c.is_ascii_digit() && !"+-.eE".contains(c))
+                .unwrap_or(s.len())];
+            for end in (1..=s.len()).rev() {
+                match number::parse(&s[..end]) {
+                    Ok(Some(n)) => return Ok(n.into()),
+                    Ok(None) => {}
+                    Err(_) => return Ok(LispType::Float(s[..end].parse().unwrap_or(0.))),
+                }
+            }
+            return Ok(LispType::Integer(0));
+        }
+        Some(base) if (2..=16).contains(&base) => base as u32,
+        Some(base) => {
+            return Err(ArrowError::invalid(format!(
+                "the base {} isn't between 2 and 16",
+                base
+            )))
+        }
+    };
+    let unsigned = s.strip_prefix(['-', '+']).unwrap_or(s);
+    let digits = unsigned
+        .find(|c: char| !c.is_digit(base))
+        .unwrap_or(unsigned.len());
+    if digits == 0 {
+        return Ok(LispType::Integer(0));
+    }
+    let end = s.len() - unsigned.len() + digits;
+    i64::from_str_radix(&s[..end], base)
+        .map(LispType::Integer)
+        .map_err(|_| {
+            ArrowError::invalid(format!(
+                "the integer {} doesn't fit into 64 bits",
+                &s[..end]
+            ))
+        })
+}
+
+/// Print a number into a string.
+pub fn from_number(n: &LispType) -> Result<LispType, ArrowError> {
+    Ok(LispType::String(n.number()?.to_string()))
+}
+
+/// Format a string like `format` in elisp. `%s` prints any value and
+/// `%S` prints it readable, with quoted strings. `%d`
+/// an integer, `%f` a float, with 6 digits after the point or as many
+/// as given like in `%.2f`, and `%%` is a single `%`.
+///
+/// # Examples
+///
+/// ```
+/// use arrow::lisptype::LispType;
+/// use arrow::string::format;
+///
+/// let args = [
+///     LispType::String("%s has %d items for %.2f€".to_string()),
+///     LispType::Symbol("cart".to_string()),
+///     LispType::Integer(3),
+///     LispType::Float(9.5),
+/// ];
+///
+/// assert_eq!(
+///     format(&args).unwrap(),
+///     LispType::String("cart has 3 items for 9.50€".to_string())
+/// );
+/// ```
+pub fn format(args: &[LispType]) -> Result<LispType, ArrowError> {
+    let (template, args) = args
+        .split_first()
+        .ok_or_else(|| ArrowError::invalid("missing format string"))?;
+    let mut args = args.iter();
+    let mut next = || {
+        args.next()
+            .ok_or_else(|| ArrowError::invalid("not enough arguments for the format string"))
+    };
+
+    let mut res = String::new();
+    let mut chars = template.string()?.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            res.push(c);
+            continue;
+        }
+        let mut precision = None;
+        if chars.peek() == Some(&'.') {
+            chars.next();
+            let mut digits = String::new();
+            while let Some(d) = chars.next_if(char::is_ascii_digit) {
+                digits.push(d);
+            }
+            precision =
+                Some(digits.parse::<usize>().map_err(|_| {
+                    ArrowError::invalid("expected the number of digits after `%.`")
+                })?);
+        }
+        match (chars.next(), precision) {
+            (Some('%'), None) => res.push('%'),
+            (Some('s'), None) => res.push_str(&next()?.to_string()?),
+            (Some('S'), None) => res.push_str(&next()?.to_readable()?),
+            (Some('d'), None) => {
+                let i = match next()?.number()? {
+                    Number::Integer(i) => i,
+                    Number::Float(f) => f.trunc() as i64,
+                };
+                res.push_str(&i.to_string());
+            }
+            (Some('f'), precision) => {
+                let f = next()?.num()?;
+                res.push_str(&format!("{:.*}", precision.unwrap_or(6), f));
+            }
+            (Some(d), _) => {
+                return Err(ArrowError::invalid(format!(
+                    "`%{}` isn't a format directive, use `%s`, `%S`, `%d`, `%f` or `%%`",
+                    d
+                )))
+            }
+            (None, _) => return Err(ArrowError::invalid("the format string ends with `%`")),
+        }
+    }
+    Ok(LispType::String(res))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn s(s: &str) -> LispType {
+        LispType::String(s.to_string())
+    }
+
     #[test]
     fn test_append() {
         let s = String::from("Hello ");
         let exp = String::from("Hello World");
         assert_eq!(s.append("World".to_string()), exp);
     }
+
+    #[test]
+    fn test_search_counts_characters() {
+        assert_eq!(search(&s("ö"), &s("äöü"), 0).unwrap(), LispType::Integer(1));
+        assert_eq!(search(&s("ü"), &s("äöü"), 2).unwrap(), LispType::Integer(2));
+        assert_eq!(
+            search(&s("ä"), &s("äöü"), 1).unwrap(),
+            LispType::Bool(false)
+        );
+        assert_eq!(search(&s(""), &s("äöü"), 3).unwrap(), LispType::Integer(3));
+        assert!(search(&s("a"), &s("äöü"), 4).is_err());
+    }
+
+    #[test]
+    fn test_format_errors() {
+        for args in [
+            vec![s("%d")],
+            vec![s("%q"), s("a")],
+            vec![s("100%")],
+            vec![s("%.xf"), LispType::Float(1.)],
+            vec![s("%d"), s("a")],
+        ] {
+            assert!(format(&args).is_err(), "{:?}", args);
+        }
+    }
 }
@@ -1,70 +1,71 @@
-use crate::expression::Expression;
+use crate::environment::Environment;
+use crate::error::ArrowError;
+use crate::expression::{Arity, Expression};
 use crate::lisptype::LispType;
+use crate::tokenize::{ast, create_lisptypes};
 
 #[test]
-fn test_create_defun() -> Result<(), &'static str> {
+fn test_create_defun() -> Result<(), ArrowError> {
     let fn_name = "defun";
-    let args = vec![LispType::String("nt".to_string()), LispType::Number(22.)];
-    let mut expr = Expression::create(fn_name, args)?;
-    assert_eq!(
-        expr.run(&mut vec![LispType::Symbol("nt".to_string())])?
-            .num(&mut vec![])?,
-        22.
-    );
+    let args = vec![LispType::Symbol("nt".to_string()), LispType::Integer(22)];
+    let expr = Expression::create(fn_name, args)?;
+    let env = Environment::default();
+    assert_eq!(expr.run(&env)?, LispType::Symbol("nt".to_string()));
+    assert_eq!(env.function("nt").unwrap().call(vec![], &env)?.num()?, 22.);
     Ok(())
 }
 
 #[test]
-fn test_create_add() -> Result<(), &'static str> {
+fn test_create_add() -> Result<(), ArrowError> {
     let name = "+";
-    let args = vec![LispType::Number(33.), LispType::Number(22.)];
-    let mut expr = Expression::create(name, args)?;
-    assert_eq!(expr.run(&mut vec![])?.num(&mut vec![])?, 55.);
+    let args = vec![LispType::Integer(33), LispType::Integer(22)];
+    let expr = Expression::create(name, args)?;
+    assert_eq!(expr.run(&Environment::default())?.num()?, 55.);
     Ok(())
 }
 
 #[test]
-fn test_create_multiply() -> Result<(), &'static str> {
+fn test_create_multiply() -> Result<(), ArrowError> {
     let name = "*";
-    let args = vec![LispType::Number(3.), LispType::Number(2.)];
-    let mut expr = Expression::create(name, args)?;
-    assert_eq!(expr.run(&mut vec![])?.num(&mut vec![])?, 6.);
+    let args = vec![LispType::Integer(3), LispType::Integer(2)];
+    let expr = Expression::create(name, args)?;
+    assert_eq!(expr.run(&Environment::default())?.num()?, 6.);
     Ok(())
 }
 
 #[test]
-fn test_create_concat() -> Result<(), &'static str> {
+fn test_create_concat() -> Result<(), ArrowError> {
     let name = "concat";
     let args = vec![
         LispType::String("h ".to_string()),
         LispType::String("w".to_string()),
     ];
-    let mut expr = Expression::create(name, args)?;
+    let expr = Expression::create(name, args)?;
     assert_eq!(
-        expr.run(&mut vec![])?.to_string(&mut vec![])?,
+        expr.run(&Environment::default())?.to_string()?,
         "h w".to_string()
     );
     Ok(())
 }
 
 #[test]
-fn test_create_equal() -> Result<(), &'static str> {
+fn test_create_equal() -> Result<(), ArrowError> {
     let name = "equal";
     let args = vec![
         LispType::String("w".to_string()),
         LispType::String("w".to_string()),
     ];
-    let mut expr = Expression::create(name, args)?;
-    assert!(expr.run(&mut vec![])?.bool()?);
+    let expr = Expression::create(name, args)?;
+    assert!(expr.run(&Environment::default())?.bool()?);
     Ok(())
 }
 
 #[test]
-fn test_create_print() -> Result<(), &'static str> {
+fn test_create_print() -> Result<(), ArrowError> {
     let name = "print";
     let args = vec![LispType::String("hw".to_string())];
-    let mut expr = Expression::create(name, args)?;
-    assert!(!expr.run(&mut vec![])?.bool()?);
+    let expr = Expression::create(name, args)?;
+    assert!(!expr.run(&Environment::default())?.bool()?);
     Ok(())
 }
 
@@ -72,6 +73,67 @@ fn test_create_print() -> Result<(), &'static str> {
 #[should_panic]
 fn test_create_fail() {
     let name = "hello";
-    let mut expr = Expression::create(name, vec![]).unwrap();
-    assert!(!expr.run(&mut vec![]).unwrap().bool().unwrap());
+    let expr = Expression::create(name, vec![]).unwrap();
+    assert!(!expr.run(&Environment::default()).unwrap().bool().unwrap());
+}
+
+#[test]
+fn test_type_error() {
+    let lisptype = create_lisptypes(ast("(+ 1\n   (* 2 \"a\"))").unwrap()).unwrap();
+    let err = lisptype[0].run(&Environment::default()).unwrap_err();
+    match err {
+        ArrowError::Type {
+            expected,
+            found,
+            span: Some(span),
+        } => {
+            assert_eq!((expected, found), ("number", "string"));
+            assert_eq!((span.start.line, span.start.column), (2, 4));
+        }
+        e => panic!("unexpected error: {:?}", e),
+    }
+}
+
+#[test]
+fn test_parse_error() {
+    let err = ast("(print \"hello)").unwrap_err();
+    assert!(matches!(err, ArrowError::Parse { .. }));
+    assert_eq!(
+        err.to_string(),
+        "parse error: unterminated string literal at 1:8"
+    );
+}
+
+#[test]
+fn test_arity_checked_at_parse_time() {
+    let err = create_lisptypes(ast("(progn\n  (mod 1))").unwrap()).unwrap_err();
+    match &err {
+        ArrowError::Arity {
+            name,
+            expected,
+            found,
+            span: Some(span),
+        } => {
+            assert_eq!(name, "mod");
+            assert_eq!(*expected, Arity::Exact(2));
+            assert_eq!(*found, 1);
+            assert_eq!((span.start.line, span.start.column), (2, 3));
+        }
+        e => panic!("unexpected error: {:?}", e),
+    }
+    assert_eq!(
+        err.to_string(),
+        "`mod` expects exactly 2 arguments, but got 1 at 2:3"
+    );
+    assert!(create_lisptypes(ast("(mod 1 2 3)").unwrap()).is_err());
+}
+
+#[test]
+fn test_arity_checked_on_create() {
+    let args = vec![LispType::Integer(1)];
+    assert!(matches!(
+        Expression::create("mod", args),
+        Err(ArrowError::Arity { .. })
+    ));
+    assert!(Expression::create("progn", vec![]).is_ok());
 }
@@ -1,16 +1,35 @@
+use crate::error::ArrowError;
 use crate::expression::{Expression, Func};
+use crate::function::Parameters;
+use crate::hashtable;
+use crate::lexer::{lex, Span, Token, TokenKind};
 use crate::lisptype::LispType;
+use crate::list;
+use crate::macros;
+use crate::stack;
+use crate::vector::Vector;
 
 #[derive(Default, PartialEq, Debug, Clone)]
 pub struct TokenContainer {
     pub name: String,
     pub children: Vec<ChildrenType>,
+    pub span: Span,
 }
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum ChildrenType {
     Container(TokenContainer),
-    Else(String),
+    Else(Token),
+}
+
+impl ChildrenType {
+    /// The part of the source code this child was read from.
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Container(c) => c.span,
+            Self::Else(t) => t.span,
+        }
+    }
 }
 
 impl TokenContainer {
@@ -23,103 +42,839 @@ impl TokenContainer {
     }
 }
 
-/// Create an ast from a string. After creating the ast, it can be
-/// passed into the create_code function to make it executable.
+/// How deep lists can be nested, before the code is rejected.
+/// Compiling them fails earlier, if the stack is used up before.
+pub(crate) const MAX_DEPTH: usize = 256;
+
+/// Create an ast from a string. Every top-level datum becomes one
+/// [ChildrenType]: a list or vector is a `Container`, an atom like a
+/// number, string or symbol is a single token. After creating the
+/// ast, it can be passed into [create_lisptypes] to make it
+/// executable.
 ///
 /// # Examples
 ///
 /// ```
-/// use arrow::tokenize::{ast, TokenContainer, ChildrenType};
+/// use arrow::tokenize::{ast, ChildrenType};
 ///
-/// let code = "(+ 4 5)";
-/// let exp = TokenContainer {
-///     name: "+".to_string(),
-///     children: vec![
-///         ChildrenType::Else("4".to_string()),
-///         ChildrenType::Else("5".to_string()),
-///     ]
-/// };
+/// let code = "(+ 4 \"a b\") 42";
+/// let data = ast(code).unwrap();
 ///
-/// assert_eq!(ast(code)[0], exp);
+/// match &data[0] {
+///     ChildrenType::Container(container) => {
+///         assert_eq!(container.name, "+");
+///         assert_eq!(container.span.end.column, 11);
+///     }
+///     _ => panic!("expected a list"),
+/// }
+/// match &data[1] {
+///     ChildrenType::Else(token) => assert_eq!(token.text, "42"),
+///     _ => panic!("expected a token"),
+/// }
 /// ```
-pub fn ast(code: &str) -> Vec<TokenContainer> {
-    let mut lasttokenbracket = false;
+pub fn ast(code: &str) -> Result<Vec<ChildrenType>, ArrowError> {
+    let mut name_expected = false;
     let mut working_stack: Vec<TokenContainer> = vec![];
+    // The depths of the lists created by prefixes like `'`, together
+    // with the prefix. They are closed as soon as their argument is
+    // complete.
+    let mut prefixes: Vec<(usize, String)> = vec![];
+    let mut res: Vec<ChildrenType> = vec![];
 
-    for token in code
-        .replace("(", " ( ")
-        .replace(")", " ) ")
-        .split_whitespace()
-    {
-        if token == "(" {
-            working_stack.push(TokenContainer::default());
-
-            lasttokenbracket = true;
-            continue;
-        } else if token == ")" {
-            let container_done = working_stack.pop().unwrap();
-            if let Some(working) = working_stack.last_mut() {
-                working.add_child(container_done);
-            } else {
-                working_stack.push(container_done);
+    for token in lex(code)? {
+        let prefix = prefix(token.kind);
+        let opens = match token.kind {
+            TokenKind::OpenParen => Some(""),
+            TokenKind::OpenBracket => Some(VECTOR),
+            _ => prefix,
+        };
+        if let Some(name) = opens {
+            if working_stack.len() == MAX_DEPTH {
+                return Err(ArrowError::parse(
+                    "lists are nested too deep",
+                    Some(token.span),
+                ));
+            }
+            working_stack.push(TokenContainer {
+                name: name.to_string(),
+                span: token.span,
+                ..TokenContainer::default()
+            });
+            if prefix.is_some() {
+                prefixes.push((working_stack.len(), token.text));
             }
-        } else if token == "[" || token == "]" {
+            name_expected = token.kind == TokenKind::OpenParen;
             continue;
-        } else if lasttokenbracket {
-            let working = working_stack.last_mut().unwrap();
-            working.set_name(token);
+        }
 
-            lasttokenbracket = false;
-        } else {
-            let working = working_stack.last_mut().unwrap();
-            working.children.push(ChildrenType::Else(token.to_string()));
+        match token.kind {
+            TokenKind::CloseParen | TokenKind::CloseBracket => {
+                if let Some((_, text)) = prefixes.last().filter(|p| p.0 == working_stack.len()) {
+                    return Err(ArrowError::parse(
+                        format!("expected a form after `{}`", text),
+                        Some(token.span),
+                    ));
+                }
+                let closes_vector = token.kind == TokenKind::CloseBracket;
+                let mut container_done = match working_stack.pop() {
+                    Some(c) if (c.name == VECTOR) == closes_vector => c,
+                    other => {
+                        let message = match (other, closes_vector) {
+                            (None, true) => "unmatched `]`",
+                            (None, false) => "unmatched `)`",
+                            (Some(_), true) => "expected `)` to close the list",
+                            (Some(_), false) => "expected `]` to close the vector",
+                        };
+                        return Err(ArrowError::parse(message, Some(token.span)));
+                    }
+                };
+                if let Some(dot) = container_done.children.iter().position(is_dot) {
+                    if dot + 2 != container_done.children.len() {
+                        return Err(ArrowError::parse(
+                            "expected exactly one form after `.`",
+                            Some(container_done.children[dot].span()),
+                        ));
+                    }
+                }
+                container_done.span = container_done.span.to(token.span);
+                if let Some(working) = working_stack.last_mut() {
+                    working.add_child(container_done);
+                } else {
+                    res.push(ChildrenType::Container(container_done));
+                }
+            }
+            TokenKind::Dot => {
+                let depth = working_stack.len();
+                let prefixed = prefixes.last().map(|p| p.0) == Some(depth);
+                match working_stack.last_mut() {
+                    Some(working)
+                        if !name_expected
+                            && !prefixed
+                            && working.name != VECTOR
+                            && !working.children.iter().any(is_dot) =>
+                    {
+                        working.children.push(ChildrenType::Else(token))
+                    }
+                    _ => return Err(ArrowError::parse("unexpected `.`", Some(token.span))),
+                }
+            }
+            _ => match working_stack.last_mut() {
+                Some(working) if name_expected => working.set_name(&token.text),
+                Some(working) => working.children.push(ChildrenType::Else(token)),
+                None => res.push(ChildrenType::Else(token)),
+            },
+        }
+        name_expected = false;
+
+        while prefixes.last().map(|p| p.0) == Some(working_stack.len()) {
+            prefixes.pop();
+            let mut prefixed = match working_stack.pop() {
+                Some(prefixed) => prefixed,
+                None => break,
+            };
+            if let Some(last) = prefixed.children.last() {
+                prefixed.span = prefixed.span.to(last.span());
+            }
+            match working_stack.last_mut() {
+                Some(working) => working.add_child(prefixed),
+                None => res.push(ChildrenType::Container(prefixed)),
+            }
         }
     }
 
-    working_stack
+    if let Some(unclosed) = working_stack.last() {
+        let message = match prefixes.last().filter(|p| p.0 == working_stack.len()) {
+            Some((_, text)) => format!("expected a form after `{}`", text),
+            None if unclosed.name == VECTOR => "unclosed `[`".to_string(),
+            None => "unclosed `(`".to_string(),
+        };
+        return Err(ArrowError::parse(message, Some(unclosed.span)));
+    }
+
+    Ok(res)
 }
 
+/// The name of the list, that a prefix like `'` is short for, so
+/// `'a` is read as `(quote a)`.
+fn prefix(kind: TokenKind) -> Option<&'static str> {
+    match kind {
+        TokenKind::Quote => Some("quote"),
+        TokenKind::FunctionQuote => Some("function"),
+        TokenKind::Backquote => Some(BACKQUOTE),
+        TokenKind::Comma => Some(UNQUOTE),
+        TokenKind::CommaAt => Some(SPLICE),
+        TokenKind::Record => Some(RECORD),
+        _ => None,
+    }
+}
+
+/// The names of the lists, that backquote, `,` and `,@` are read as.
+const BACKQUOTE: &str = "`";
+const UNQUOTE: &str = ",";
+const SPLICE: &str = ",@";
+/// The name of the list, that `#s(...)` is read as.
+const RECORD: &str = "#s";
+/// The name of the list, that a vector like `[a b]` is read as.
+const VECTOR: &str = "[";
+
 /// Takes the ast and generates the LispTypes and bundles them into single LispTypes.
-/// One Arrow Function will result in one LispType.
-pub fn create_lisptypes(input: Vec<TokenContainer>) -> Result<Vec<LispType>, &'static str> {
-    let mut res: Vec<LispType> = vec![];
-    for container in input {
-        let mut args: Vec<LispType> = vec![];
-
-        for child in container.children {
-            match child {
-                ChildrenType::Container(c) => {
-                    let lts = create_lisptypes(vec![c])?;
-
-                    for lt in lts {
-                        args.push(lt);
-                    }
-                }
-                ChildrenType::Else(e) => args.push(LispType::new(&[e], true)?),
+/// Every top-level datum results in one LispType, an atom like `x`
+/// or `42` is compiled like an argument of a list.
+pub fn create_lisptypes(input: Vec<ChildrenType>) -> Result<Vec<LispType>, ArrowError> {
+    input.into_iter().map(top_level).collect()
+}
+
+/// Compile a top-level datum. A symbol is wrapped into a `progn`, so
+/// an error while looking up its variable has the span of the token.
+fn top_level(datum: ChildrenType) -> Result<LispType, ArrowError> {
+    let span = datum.span();
+    match child(datum)? {
+        symbol @ LispType::Symbol(_) => Ok(LispType::Expression(Expression {
+            func: Func::Progn,
+            args: vec![symbol],
+            span: Some(span),
+        })),
+        other => Ok(other),
+    }
+}
+
+/// Compile a single list into an [Expression].
+fn expression(container: TokenContainer) -> Result<LispType, ArrowError> {
+    let span = Some(container.span);
+    let _guard = nested(span)?;
+    let func = match container.name.as_str() {
+        // `()` is another way to write `nil`.
+        "" if container.children.is_empty() => return Ok(LispType::Bool(false)),
+        BACKQUOTE | RECORD | VECTOR | UNQUOTE | SPLICE => return syntax(container),
+        _ if container.children.iter().any(is_dot) => {
+            return Err(ArrowError::parse("a dotted list can't be run", span))
+        }
+        name => Func::resolve(name).map_err(|e| e.with_span(span))?,
+    };
+    let arguments: Arguments = match func {
+        Func::Call => call,
+        Func::Invoke => invoke,
+        Func::Defun => definition,
+        Func::Defmacro => macro_definition,
+        Func::Lambda => lambda,
+        Func::Quote => data,
+        Func::Cond => cond,
+        Func::Case => case,
+        Func::Let | Func::LetStar => local,
+        Func::Dotimes | Func::Dolist => iteration,
+        _ => code,
+    };
+    let args = arguments(container)?;
+
+    func.check_arity(args.len())
+        .map_err(|e| e.with_span(span))?;
+    Ok(LispType::Expression(Expression { func, args, span }))
+}
+
+/// Compile a list, that was created by syntax like `` ` `` or `[`
+/// instead of a call.
+fn syntax(container: TokenContainer) -> Result<LispType, ArrowError> {
+    let span = container.span;
+    match container.name.as_str() {
+        BACKQUOTE => backquote(single(container)?),
+        RECORD => Ok(quote(record(single(container)?)?, span)),
+        VECTOR => Ok(quote(datum(ChildrenType::Container(container))?, span)),
+        _ => Err(ArrowError::parse(
+            format!(
+                "`{}` can only be used inside of a backquote",
+                container.name
+            ),
+            Some(span),
+        )),
+    }
+}
+
+/// A function, that compiles the arguments of a list for one kind of
+/// [Func]. Every kind has its own function, so the stack frame of
+/// [expression] stays small, even in debug builds.
+type Arguments = fn(TokenContainer) -> Result<Vec<LispType>, ArrowError>;
+
+/// The arguments of a function, that are all code.
+fn code(list: TokenContainer) -> Result<Vec<LispType>, ArrowError> {
+    list.children.into_iter().map(child).collect()
+}
+
+/// The arguments of `quote`, that are all data.
+fn data(list: TokenContainer) -> Result<Vec<LispType>, ArrowError> {
+    list.children.into_iter().map(datum).collect()
+}
+
+/// The arguments of a call to a function, that isn't built in.
+fn invoke(list: TokenContainer) -> Result<Vec<LispType>, ArrowError> {
+    let mut args = vec![LispType::Symbol(list.name)];
+    for c in list.children {
+        args.push(child(c)?);
+    }
+    Ok(args)
+}
+
+/// The arguments of a call to a function, that might be a macro. The
+/// data of the arguments is quoted, in case the macro has to be
+/// expanded.
+fn call(list: TokenContainer) -> Result<Vec<LispType>, ArrowError> {
+    let data = list
+        .children
+        .iter()
+        .cloned()
+        .map(datum)
+        .collect::<Result<_, _>>()?;
+    let mut args = vec![
+        LispType::Symbol(list.name),
+        quote(list::list(data), list.span),
+    ];
+    for c in list.children {
+        args.push(child(c)?);
+    }
+    Ok(args)
+}
+
+/// The arguments of `defun` and `defmacro`.
+fn definition(list: TokenContainer) -> Result<Vec<LispType>, ArrowError> {
+    let span = Some(list.span);
+    let mut children = list.children.into_iter();
+    let mut args = vec![function_name(children.next(), span)?];
+    args.extend(parameters(children.next(), span)?);
+    args.push(progn(children, span)?);
+    Ok(args)
+}
+
+/// The arguments of `defmacro`. The name is declared as a macro right
+/// away, so the calls after the definition keep their data.
+fn macro_definition(list: TokenContainer) -> Result<Vec<LispType>, ArrowError> {
+    let args = definition(list)?;
+    if let Some(LispType::Symbol(name)) = args.first() {
+        macros::declare(name);
+    }
+    Ok(args)
+}
+
+/// The arguments of `lambda`.
+fn lambda(list: TokenContainer) -> Result<Vec<LispType>, ArrowError> {
+    let span = Some(list.span);
+    let mut children = list.children.into_iter();
+    let mut args = parameters(children.next(), span)?;
+    args.push(progn(children, span)?);
+    Ok(args)
+}
+
+/// The arguments of `cond`, its clauses.
+fn cond(list: TokenContainer) -> Result<Vec<LispType>, ArrowError> {
+    clauses(list.children.into_iter(), false)
+}
+
+/// The arguments of `case`, the key followed by the clauses.
+fn case(list: TokenContainer) -> Result<Vec<LispType>, ArrowError> {
+    let mut children = list.children.into_iter();
+    let mut args = children
+        .next()
+        .map(child)
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+    args.extend(clauses(children, true)?);
+    Ok(args)
+}
+
+/// The arguments of `let` and `let*`.
+fn local(list: TokenContainer) -> Result<Vec<LispType>, ArrowError> {
+    let span = Some(list.span);
+    let mut children = list.children.into_iter();
+    let mut args = match children.next() {
+        Some(ChildrenType::Container(list)) => bindings(list)?,
+        Some(ChildrenType::Else(t)) if t.text == "nil" => vec![],
+        other => {
+            return Err(ArrowError::parse(
+                "expected a binding list like `((var value))`",
+                other.map(|c| c.span()).or(span),
+            ))
+        }
+    };
+    args.push(progn(children, span)?);
+    Ok(args)
+}
+
+/// The arguments of `dotimes` and `dolist`.
+fn iteration(list: TokenContainer) -> Result<Vec<LispType>, ArrowError> {
+    let span = Some(list.span);
+    let mut children = list.children.into_iter();
+    let mut args = binding(children.next(), span)?;
+    for c in children {
+        args.push(child(c)?);
+    }
+    Ok(args)
+}
+
+/// Enter a list while compiling it. Fails, if the lists are nested
+/// too deep for the stack, that is left.
+fn nested(span: Option<Span>) -> Result<stack::Guard, ArrowError> {
+    stack::enter().ok_or_else(|| ArrowError::parse("lists are nested too deep", span))
+}
+
+/// Compile a single argument of a list.
+fn child(child: ChildrenType) -> Result<LispType, ArrowError> {
+    match child {
+        ChildrenType::Container(c) => expression(c),
+        ChildrenType::Else(e) => atom(e.text, e.span),
+    }
+}
+
+fn atom(text: String, span: Span) -> Result<LispType, ArrowError> {
+    LispType::new(&[text], true).map_err(|e| e.with_span(Some(span)))
+}
+
+/// The only child of a list created by a prefix like `'`.
+fn single(container: TokenContainer) -> Result<ChildrenType, ArrowError> {
+    let span = Some(container.span);
+    let mut children = container.children.into_iter();
+    match (children.next(), children.next()) {
+        (Some(child), None) => Ok(child),
+        _ => Err(ArrowError::parse(
+            format!("`{}` needs exactly one argument", container.name),
+            span,
+        )),
+    }
+}
+
+/// Read a child as data instead of code, like `quote` does. Lists
+/// become cons cells and symbols stay symbols.
+fn datum(child: ChildrenType) -> Result<LispType, ArrowError> {
+    let _guard = nested(Some(child.span()))?;
+    match child {
+        ChildrenType::Else(e) => atom(e.text, e.span),
+        ChildrenType::Container(c) if c.name == RECORD => record(single(c)?),
+        ChildrenType::Container(c) if c.name == VECTOR => Ok(LispType::Vector(Vector::new(
+            c.children
+                .into_iter()
+                .map(datum)
+                .collect::<Result<_, _>>()?,
+        ))),
+        ChildrenType::Container(c) => {
+            let (items, tail) = dotted(c);
+            let tail = match tail {
+                Some(tail) => datum(tail)?,
+                None => LispType::Bool(false),
+            };
+            Ok(list::dotted(
+                items.into_iter().map(datum).collect::<Result<_, _>>()?,
+                tail,
+            ))
+        }
+    }
+}
+
+/// Read a record like `#s(hash-table data (a 1))`. Hash tables are
+/// the only records so far. The table is created when the code is
+/// read, so a `#s` in a function returns the same table every time.
+fn record(child: ChildrenType) -> Result<LispType, ArrowError> {
+    let span = child.span();
+    let data = match child {
+        ChildrenType::Container(c) => datum(ChildrenType::Container(c))?,
+        ChildrenType::Else(_) => {
+            return Err(ArrowError::parse("expected a list after `#s`", Some(span)))
+        }
+    };
+    hashtable::read(&data)
+        .map(LispType::HashTable)
+        .map_err(|e| e.with_span(Some(span)))
+}
+
+/// All elements of a list, including its head.
+fn items(container: TokenContainer) -> impl Iterator<Item = ChildrenType> {
+    let head = match container.name.is_empty() {
+        true => None,
+        false => Some(ChildrenType::Else(Token {
+            kind: TokenKind::Symbol,
+            text: container.name,
+            span: container.span,
+        })),
+    };
+    head.into_iter().chain(container.children)
+}
+
+/// Whether the child is the `.` of a dotted list.
+fn is_dot(child: &ChildrenType) -> bool {
+    matches!(child, ChildrenType::Else(t) if t.kind == TokenKind::Dot)
+}
+
+/// All elements of a list like [items], and the tail after the `.`,
+/// if it is a dotted list like `(a b . c)`.
+fn dotted(container: TokenContainer) -> (Vec<ChildrenType>, Option<ChildrenType>) {
+    let mut items: Vec<_> = items(container).collect();
+    let tail = match items.len() {
+        n if n >= 2 && is_dot(&items[n - 2]) => items.pop(),
+        _ => None,
+    };
+    if tail.is_some() {
+        items.pop();
+    }
+    (items, tail)
+}
+
+/// Compile data into code, like the expansion of a macro. This is the
+/// reverse of `quote`, the list `(+ 1 2)` becomes the code `(+ 1 2)`.
+/// The code wasn't read from the source, so it has no spans.
+///
+/// # Examples
+///
+/// ```
+/// use arrow::environment::Environment;
+/// use arrow::lisptype::LispType;
+/// use arrow::list;
+/// use arrow::tokenize::compile;
+///
+/// let data = list::list(vec![
+///     LispType::Symbol("+".to_string()),
+///     LispType::Integer(1),
+///     LispType::Integer(2),
+/// ]);
+/// let code = compile(&data).unwrap();
+///
+/// assert_eq!(code.run(&Environment::default()).unwrap(), LispType::Integer(3));
+/// ```
+pub fn compile(data: &LispType) -> Result<LispType, ArrowError> {
+    Ok(forget_spans(child(source(data)?)?))
+}
+
+/// Turn data back into the ast it could have been read from.
+fn source(data: &LispType) -> Result<ChildrenType, ArrowError> {
+    let _guard = nested(None)?;
+    let (items, tail) = match data {
+        LispType::Cons(_) => {
+            let mut items = list::iter(data);
+            (items.by_ref().cloned().collect::<Vec<_>>(), items.tail())
+        }
+        LispType::Vector(v) => {
+            let mut container = TokenContainer {
+                name: VECTOR.to_string(),
+                ..TokenContainer::default()
+            };
+            for item in v.to_vec() {
+                container.children.push(source(&item)?);
             }
+            return Ok(ChildrenType::Container(container));
         }
+        atom => {
+            return Ok(ChildrenType::Else(Token {
+                kind: TokenKind::Symbol,
+                text: text(atom)?,
+                span: Span::default(),
+            }))
+        }
+    };
 
-        res.push(LispType::Expression(Expression {
-            func: Func::new(container.name.as_str())?,
-            args,
+    let mut container = TokenContainer::default();
+    let mut children = &items[..];
+    if let Some(head) = items
+        .first()
+        .filter(|h| !matches!(h, LispType::Cons(_) | LispType::Vector(_)))
+    {
+        container.set_name(&text(head)?);
+        children = &items[1..];
+    }
+    for item in children {
+        container.children.push(source(item)?);
+    }
+    if *tail != LispType::Bool(false) {
+        container.children.push(ChildrenType::Else(Token {
+            kind: TokenKind::Dot,
+            text: ".".to_string(),
+            span: Span::default(),
         }));
+        container.children.push(source(tail)?);
+    }
+    Ok(ChildrenType::Container(container))
+}
+
+/// The text of an atom, as it would be written in the source code.
+fn text(atom: &LispType) -> Result<String, ArrowError> {
+    match atom {
+        LispType::Float(f) if !f.is_finite() => Err(ArrowError::invalid(format!(
+            "{} can't be compiled into code",
+            atom.to_string()?
+        ))),
+        LispType::Integer(_)
+        | LispType::Float(_)
+        | LispType::Bool(_)
+        | LispType::String(_)
+        | LispType::Symbol(_) => atom.to_readable(),
+        other => Err(ArrowError::invalid(format!(
+            "a {} can't be compiled into code",
+            other.type_name()
+        ))),
+    }
+}
+
+/// Remove the spans of compiled code.
+fn forget_spans(code: LispType) -> LispType {
+    match code {
+        LispType::Expression(e) => LispType::Expression(Expression {
+            func: e.func,
+            args: e.args.into_iter().map(forget_spans).collect(),
+            span: None,
+        }),
+        other => other,
+    }
+}
+
+/// Wrap data into a `quote` expression.
+fn quote(data: LispType, span: Span) -> LispType {
+    LispType::Expression(Expression {
+        func: Func::Quote,
+        args: vec![data],
+        span: Some(span),
+    })
+}
+
+/// Compile a backquote template into the code that builds it. Parts
+/// without `,` and `,@` are quoted, `,x` is replaced by the value of
+/// `x` and the elements of the list `,@x` are spliced into the
+/// surrounding list.
+///
+/// `(a ,b ,@c)` is compiled into `(append (list 'a) (list b) c)`.
+fn backquote(template: ChildrenType) -> Result<LispType, ArrowError> {
+    let span = template.span();
+    let _guard = nested(Some(span))?;
+    let container = match template {
+        ChildrenType::Else(e) => return Ok(quote(atom(e.text, e.span)?, span)),
+        ChildrenType::Container(c) => c,
+    };
+    match container.name.as_str() {
+        UNQUOTE => return child(single(container)?),
+        RECORD => return Ok(quote(record(single(container)?)?, span)),
+        VECTOR => {
+            // `[a ,b]` is built like the list `(a ,b)` and then turned
+            // into a vector.
+            let list = TokenContainer {
+                name: String::new(),
+                ..container
+            };
+            return Ok(LispType::Expression(Expression {
+                func: Func::Vconcat,
+                args: vec![backquote(ChildrenType::Container(list))?],
+                span: Some(span),
+            }));
+        }
+        SPLICE => {
+            return Err(ArrowError::parse(
+                "`,@` can only be used inside of a list",
+                Some(span),
+            ))
+        }
+        BACKQUOTE => {
+            return Err(ArrowError::parse(
+                "nested backquotes are not supported",
+                Some(span),
+            ))
+        }
+        _ => {}
+    }
+
+    let (items, tail) = dotted(container);
+    let mut parts = vec![];
+    for item in items {
+        parts.push(match item {
+            ChildrenType::Container(c) if c.name == SPLICE => child(single(c)?)?,
+            item => LispType::Expression(Expression {
+                func: Func::List,
+                args: vec![backquote(item)?],
+                span: Some(span),
+            }),
+        });
+    }
+    // The last part is copied as well, so the result never shares
+    // cells with a spliced list. Only the tail of a dotted list like
+    // `(a . ,b)` is used as it is.
+    parts.push(match tail {
+        Some(tail) => backquote(tail)?,
+        None => LispType::Bool(false),
+    });
+    Ok(LispType::Expression(Expression {
+        func: Func::Append,
+        args: parts,
+        span: Some(span),
+    }))
+}
+
+/// Read the name of a function in `defun` or `defmacro`. It has to
+/// be a symbol.
+fn function_name(name: Option<ChildrenType>, span: Option<Span>) -> Result<LispType, ArrowError> {
+    if let Some(ChildrenType::Else(token)) = &name {
+        if let LispType::Symbol(s) = atom(token.text.clone(), token.span)? {
+            return Ok(LispType::Symbol(s));
+        }
+    }
+    Err(ArrowError::parse(
+        "expected the name of the function",
+        name.map(|n| n.span()).or(span),
+    ))
+}
+
+/// Read the parameter list of `defun`, `defmacro` and `lambda`, like `(a &optional b &rest c)`.
+fn parameters(list: Option<ChildrenType>, span: Option<Span>) -> Result<Vec<LispType>, ArrowError> {
+    let list = match list {
+        Some(ChildrenType::Container(list)) => list,
+        other => {
+            return Err(ArrowError::parse(
+                "expected a parameter list like `(a b)`",
+                other.map(|c| c.span()).or(span),
+            ))
+        }
+    };
+    let span = Some(list.span);
+    let params = atoms(list, "parameters must be symbols")?;
+    Parameters::parse(&params).map_err(|e| e.with_span(span))?;
+    Ok(params)
+}
+
+/// Compile the rest of a list into a single `progn` expression.
+fn progn(
+    children: impl Iterator<Item = ChildrenType>,
+    span: Option<Span>,
+) -> Result<LispType, ArrowError> {
+    Ok(LispType::Expression(Expression {
+        func: Func::Progn,
+        args: children.map(child).collect::<Result<_, _>>()?,
+        span,
+    }))
+}
+
+/// Compile the `((var value) ...)` list of `let` and `let*` into pairs
+/// of arguments. A variable without a value, written as `var` or
+/// `(var)`, is bound to `nil`.
+fn bindings(list: TokenContainer) -> Result<Vec<LispType>, ArrowError> {
+    let mut res = vec![];
+    if !list.name.is_empty() {
+        res.push(atom(list.name, list.span)?);
+        res.push(LispType::Bool(false));
+    }
+    for binding in list.children {
+        match binding {
+            ChildrenType::Else(e) => {
+                res.push(atom(e.text, e.span)?);
+                res.push(LispType::Bool(false));
+            }
+            ChildrenType::Container(c) if !c.name.is_empty() && c.children.len() <= 1 => {
+                res.push(atom(c.name, c.span)?);
+                match c.children.into_iter().next() {
+                    Some(value) => res.push(child(value)?),
+                    None => res.push(LispType::Bool(false)),
+                }
+            }
+            ChildrenType::Container(c) => {
+                return Err(ArrowError::parse(
+                    "expected a binding like `(var value)`",
+                    Some(c.span),
+                ))
+            }
+        }
+    }
+    Ok(res)
+}
+
+/// Compile the `(var value [result])` list at the start of `dotimes`
+/// and `dolist` into three arguments. The result defaults to `nil`.
+fn binding(list: Option<ChildrenType>, span: Option<Span>) -> Result<Vec<LispType>, ArrowError> {
+    let list = match list {
+        Some(ChildrenType::Container(c))
+            if !c.name.is_empty() && (1..=2).contains(&c.children.len()) =>
+        {
+            c
+        }
+        other => {
+            return Err(ArrowError::parse(
+                "expected a list like `(var value)`",
+                other.map(|c| c.span()).or(span),
+            ))
+        }
+    };
+    let mut res = vec![atom(list.name, list.span)?];
+    for c in list.children {
+        res.push(child(c)?);
+    }
+    if res.len() < 3 {
+        res.push(LispType::Bool(false));
+    }
+    Ok(res)
+}
+
+/// Compile the clauses of `cond` and `case`. Every clause becomes two
+/// arguments: its head and a `progn` with the rest of the clause.
+///
+/// For `case`, the head is a key or a list of keys, which are not
+/// evaluated. A clause with a list of keys is split into one pair per
+/// key, and the default clause (`t` or `otherwise`) gets the key `t`.
+fn clauses(
+    children: impl Iterator<Item = ChildrenType>,
+    keys: bool,
+) -> Result<Vec<LispType>, ArrowError> {
+    let mut res = vec![];
+    for clause in children {
+        let clause = match clause {
+            ChildrenType::Container(c) => c,
+            ChildrenType::Else(e) => {
+                return Err(ArrowError::parse("clauses must be lists", Some(e.span)))
+            }
+        };
+        let span = Some(clause.span);
+        let mut rest = clause.children.into_iter();
+
+        let heads = if !clause.name.is_empty() {
+            let name = match clause.name.as_str() {
+                "otherwise" if keys => "t".to_string(),
+                _ => clause.name,
+            };
+            vec![atom(name, clause.span)?]
+        } else {
+            match rest.next() {
+                Some(ChildrenType::Container(list)) if keys => atoms(list, "keys must be atoms")?,
+                Some(head) => vec![child(head)?],
+                None => return Err(ArrowError::parse("clauses can't be empty", span)),
+            }
+        };
+
+        let body = progn(rest, span)?;
+        for head in heads {
+            res.push(head);
+            res.push(body.clone());
+        }
     }
+    Ok(res)
+}
 
+/// Read a list, that may only contain atoms, like the keys of `case`.
+/// `message` is the error for nested lists.
+fn atoms(list: TokenContainer, message: &str) -> Result<Vec<LispType>, ArrowError> {
+    let mut res = vec![];
+    if !list.name.is_empty() {
+        res.push(atom(list.name, list.span)?);
+    }
+    for key in list.children {
+        match key {
+            ChildrenType::Else(e) => res.push(atom(e.text, e.span)?),
+            ChildrenType::Container(c) => return Err(ArrowError::parse(message, Some(c.span))),
+        }
+    }
     Ok(res)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::environment::Environment;
+    use crate::lexer::Position;
 
     #[test]
     fn test_create_lisptype() {
         let test = "(+ 2 3)";
         assert_eq!(
-            create_lisptypes(ast(test)).unwrap()[0]
-                .run(&mut vec![])
+            create_lisptypes(ast(test).unwrap()).unwrap()[0]
+                .run(&Environment::default())
                 .unwrap()
-                .num(&mut vec![])
+                .num()
                 .unwrap(),
             5.
         );
@@ -129,44 +884,135 @@ mod tests {
     fn test_create_lisptype_complex() {
         let test = "(+ (* 4 5) (* 3 (* 6 2)))";
         assert_eq!(
-            create_lisptypes(ast(test)).unwrap()[0]
-                .run(&mut vec![])
+            create_lisptypes(ast(test).unwrap()).unwrap()[0]
+                .run(&Environment::default())
                 .unwrap()
-                .num(&mut vec![])
+                .num()
                 .unwrap(),
             56.
         );
     }
 
+    fn token(kind: TokenKind, text: &str, line: usize, column: usize) -> ChildrenType {
+        let start = Position { line, column };
+        let end = Position {
+            line,
+            column: column + text.chars().count() - 1,
+        };
+        ChildrenType::Else(Token {
+            kind,
+            text: text.to_string(),
+            span: Span { start, end },
+        })
+    }
+
+    fn span(start: (usize, usize), end: (usize, usize)) -> Span {
+        Span {
+            start: Position {
+                line: start.0,
+                column: start.1,
+            },
+            end: Position {
+                line: end.0,
+                column: end.1,
+            },
+        }
+    }
+
     #[test]
     fn test_create_ast_simple() {
         let test = "(mod 5 2)";
         let test_ast = TokenContainer {
             name: "mod".to_string(),
             children: vec![
-                ChildrenType::Else("5".to_string()),
-                ChildrenType::Else("2".to_string()),
+                token(TokenKind::Number, "5", 1, 6),
+                token(TokenKind::Number, "2", 1, 8),
             ],
+            span: span((1, 1), (1, 9)),
         };
-        assert_eq!(ast(test)[0], test_ast);
+        assert_eq!(ast(test).unwrap()[0], ChildrenType::Container(test_ast));
     }
 
     #[test]
     fn test_create_ast_complex() {
-        let test = "(mod 5 (add 4 5))";
+        let test = "(mod 5\n  (add 4 5))";
         let test_ast = TokenContainer {
             name: "mod".to_string(),
             children: vec![
-                ChildrenType::Else("5".to_string()),
+                token(TokenKind::Number, "5", 1, 6),
                 ChildrenType::Container(TokenContainer {
                     name: "add".to_string(),
                     children: vec![
-                        ChildrenType::Else("4".to_string()),
-                        ChildrenType::Else("5".to_string()),
+                        token(TokenKind::Number, "4", 2, 8),
+                        token(TokenKind::Number, "5", 2, 10),
                     ],
+                    span: span((2, 3), (2, 11)),
                 }),
             ],
+            span: span((1, 1), (2, 12)),
         };
-        assert_eq!(ast(test)[0], test_ast)
+        assert_eq!(ast(test).unwrap()[0], ChildrenType::Container(test_ast))
+    }
+
+    #[test]
+    fn test_create_ast_string_with_spaces() {
+        let test = "(concat \"hello world\" 'a)";
+        let test_ast = TokenContainer {
+            name: "concat".to_string(),
+            children: vec![
+                token(TokenKind::String, "\"hello world\"", 1, 9),
+                ChildrenType::Container(TokenContainer {
+                    name: "quote".to_string(),
+                    children: vec![token(TokenKind::Symbol, "a", 1, 24)],
+                    span: span((1, 23), (1, 24)),
+                }),
+            ],
+            span: span((1, 1), (1, 25)),
+        };
+        assert_eq!(ast(test).unwrap()[0], ChildrenType::Container(test_ast))
+    }
+
+    /// The first top-level list of `code`.
+    fn container(code: &str) -> TokenContainer {
+        match ast(code).unwrap().into_iter().next() {
+            Some(ChildrenType::Container(c)) => c,
+            other => panic!("expected a list, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_create_ast_atoms() {
+        let test = "42 \"hi\"\n x";
+        assert_eq!(
+            ast(test).unwrap(),
+            vec![
+                token(TokenKind::Number, "42", 1, 1),
+                token(TokenKind::String, "\"hi\"", 1, 4),
+                token(TokenKind::Symbol, "x", 2, 2),
+            ]
+        );
+        assert!(ast("x )").is_err());
+    }
+
+    #[test]
+    fn test_create_ast_prefixes() {
+        let quoted = container("(f '(a b) `c)");
+        match &quoted.children[..] {
+            [ChildrenType::Container(q), ChildrenType::Container(b)] => {
+                assert_eq!(q.name, "quote");
+                assert_eq!(q.span, span((1, 4), (1, 9)));
+                assert_eq!(b.name, "`");
+            }
+            other => panic!("unexpected children: {:?}", other),
+        }
+        assert_eq!(container("'a").name, "quote");
+        assert!(ast("(f ')").is_err());
+        assert!(ast("(f ,@)").is_err());
+    }
+
+    #[test]
+    fn test_create_ast_unbalanced() {
+        assert!(ast("(+ 1 2))").is_err());
+        assert!(ast("(+ 1 (* 2 3)").is_err());
     }
 }
@@ -0,0 +1,172 @@
+//! Vectors of arrow, like in Emacs Lisp. A vector is written like
+//! `[1 2 3]`, its elements are read as data and aren't evaluated.
+//! Unlike lists, the elements of a vector can be accessed and changed
+//! in constant time by their index. All copies of a vector refer to
+//! the same vector, so changes are visible everywhere.
+
+use std::{
+    cell::{Ref, RefCell},
+    convert::TryFrom,
+    fmt,
+    rc::Rc,
+};
+
+use crate::error::ArrowError;
+use crate::lisptype::{drop_nested, print_nested, LispType, Printer};
+use crate::list;
+
+/// A vector. Cloning it is cheap, the clone refers to the same
+/// vector.
+///
+/// # Examples
+///
+/// ```
+/// use arrow::lisptype::LispType;
+/// use arrow::vector::Vector;
+///
+/// let v = Vector::new(vec![LispType::Integer(1), LispType::Integer(2)]);
+/// let same = v.clone();
+/// v.set(1, LispType::Integer(5)).unwrap();
+///
+/// assert_eq!(same.get(1).unwrap(), LispType::Integer(5));
+/// assert!(v.get(2).is_err());
+/// ```
+#[derive(Clone)]
+pub struct Vector(Rc<RefCell<Vec<LispType>>>);
+
+impl Vector {
+    pub fn new(items: Vec<LispType>) -> Self {
+        Self(Rc::new(RefCell::new(items)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The element at `index`.
+    pub fn get(&self, index: i64) -> Result<LispType, ArrowError> {
+        let i = self.index(index)?;
+        Ok(self.0.borrow()[i].clone())
+    }
+
+    /// Replace the element at `index`.
+    pub fn set(&self, index: i64, value: LispType) -> Result<(), ArrowError> {
+        let i = self.index(index)?;
+        self.0.borrow_mut()[i] = value;
+        Ok(())
+    }
+
+    /// A copy of all elements.
+    pub fn to_vec(&self) -> Vec<LispType> {
+        self.0.borrow().clone()
+    }
+
+    /// Borrow the elements.
+    pub(crate) fn items(&self) -> Ref<'_, Vec<LispType>> {
+        self.0.borrow()
+    }
+
+    /// The address of the elements, that all copies of the vector
+    /// share.
+    pub(crate) fn address(&self) -> usize {
+        Rc::as_ptr(&self.0) as usize
+    }
+
+    /// Move the lists and vectors in this vector to `pending`, if no
+    /// other copy of the vector is left.
+    pub(crate) fn unlink(&self, pending: &mut Vec<LispType>) {
+        if Rc::strong_count(&self.0) == 1 {
+            let items = std::mem::take(&mut *self.0.borrow_mut());
+            pending.extend(items.into_iter().filter(LispType::is_nested));
+        }
+    }
+
+    fn index(&self, index: i64) -> Result<usize, ArrowError> {
+        match usize::try_from(index) {
+            Ok(i) if i < self.len() => Ok(i),
+            _ => Err(ArrowError::invalid(format!(
+                "index {} is out of range for a vector of length {}",
+                index,
+                self.len()
+            ))),
+        }
+    }
+}
+
+impl fmt::Debug for Vector {
+    /// Only the length is shown, because a vector can contain itself.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Vector")
+            .field("length", &self.len())
+            .finish()
+    }
+}
+
+impl Drop for Vector {
+    /// Free the elements with [drop_nested], so vectors, that are
+    /// nested deeply, can't overflow the stack.
+    fn drop(&mut self) {
+        let mut pending = vec![];
+        self.unlink(&mut pending);
+        drop_nested(pending);
+    }
+}
+
+impl PartialEq for Vector {
+    /// Vectors are only equal to themselves, like with `eq`.
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Get the vector out of a value, or fail with a type error.
+pub fn vector(value: &LispType) -> Result<&Vector, ArrowError> {
+    match value {
+        LispType::Vector(v) => Ok(v),
+        other => Err(ArrowError::type_error("vector", other.type_name())),
+    }
+}
+
+/// The elements of a vector or a proper list.
+pub fn sequence(value: &LispType) -> Result<Vec<LispType>, ArrowError> {
+    match value {
+        LispType::Vector(v) => Ok(v.to_vec()),
+        LispType::Bool(false) | LispType::Cons(_) => list::elements(value),
+        other => Err(ArrowError::type_error("sequence", other.type_name())),
+    }
+}
+
+/// Join vectors and lists into a new vector, like `vconcat` in elisp.
+pub fn vconcat(sequences: &[LispType]) -> Result<LispType, ArrowError> {
+    let mut items = vec![];
+    for s in sequences {
+        items.extend(sequence(s)?);
+    }
+    Ok(LispType::Vector(Vector::new(items)))
+}
+
+/// The longest vector `make-vector` creates.
+const MAX_LENGTH: i64 = 1 << 24;
+
+/// A new vector of `length` elements, that are all `init`.
+pub fn make(length: i64, init: LispType) -> Result<LispType, ArrowError> {
+    if !(0..=MAX_LENGTH).contains(&length) {
+        return Err(ArrowError::invalid(format!(
+            "the length of a vector must be between 0 and {}",
+            MAX_LENGTH
+        )));
+    }
+    let length = length as usize;
+    Ok(LispType::Vector(Vector::new(vec![init; length])))
+}
+
+/// Print a vector like `[1 2 3]`. The elements are printed with
+/// `print`.
+pub fn to_string(v: &Vector, print: Printer) -> Result<String, ArrowError> {
+    let items = print_nested(|| v.to_vec().iter().map(print).collect::<Result<Vec<_>, _>>())?;
+    Ok(format!("[{}]", items.join(" ")))
+}
@@ -1,35 +1,37 @@
+use arrow::environment::Environment;
+use arrow::error::ArrowError;
 use arrow::expression::Expression;
 use arrow::lisptype::LispType;
 
 #[test]
-fn test_simple_example_f() -> Result<(), &'static str> {
-    let mut test = LispType::Number(12.);
+fn test_simple_example_f() -> Result<(), ArrowError> {
+    let test = LispType::Integer(12);
     let exp = 12.;
-    assert_eq!(test.run(&mut vec![])?.num(&mut vec![])?, exp);
+    assert_eq!(test.run(&Environment::default())?.num()?, exp);
     Ok(())
 }
 
 #[test]
-fn test_simple_example_str() -> Result<(), &'static str> {
-    let mut test = LispType::String("Hello".to_string());
+fn test_simple_example_str() -> Result<(), ArrowError> {
+    let test = LispType::String("Hello".to_string());
     let exp = "Hello".to_string();
-    assert_eq!(test.run(&mut vec![])?.to_string(&mut vec![])?, exp);
+    assert_eq!(test.run(&Environment::default())?.to_string()?, exp);
     Ok(())
 }
 
 #[test]
-fn test_advanced_example() -> Result<(), &'static str> {
-    let mut test = LispType::Expression(Expression::create(
+fn test_advanced_example() -> Result<(), ArrowError> {
+    let test = LispType::Expression(Expression::create(
         "+",
         vec![
             LispType::Expression(Expression::create(
                 "+",
-                vec![LispType::Number(12.), LispType::Number(3.)],
+                vec![LispType::Integer(12), LispType::Integer(3)],
             )?),
-            LispType::Number(22.),
+            LispType::Integer(22),
         ],
     )?);
     let exp = 37.;
-    assert_eq!(test.run(&mut vec![])?.num(&mut vec![])?, exp);
+    assert_eq!(test.run(&Environment::default())?.num()?, exp);
     Ok(())
 }
@@ -0,0 +1,124 @@
+mod common;
+
+use arrow::error::ArrowError;
+use arrow::lisptype::LispType;
+use arrow::tokenize::ast;
+use common::eval;
+
+/// Evaluate the code and print the result, so integers and floats can
+/// be told apart.
+fn show(code: &str) -> String {
+    eval(code).unwrap().to_string().unwrap()
+}
+
+#[test]
+fn test_variadic_operators() {
+    assert_eq!(show("(+)"), "0");
+    assert_eq!(show("(+ 1 2 3 4)"), "10");
+    assert_eq!(show("(*)"), "1");
+    assert_eq!(show("(* 2 3 4)"), "24");
+    assert_eq!(show("(- 10 1 2 3)"), "4");
+    assert_eq!(show("(/ 120 2 3 4)"), "5");
+}
+
+#[test]
+fn test_unary_minus_and_reciprocal() {
+    assert_eq!(show("(- 5)"), "-5");
+    assert_eq!(show("(/ 4.0)"), "0.25");
+    assert_eq!(show("(/ 4)"), "0");
+    assert!(matches!(eval("(-)"), Err(ArrowError::Arity { .. })));
+}
+
+#[test]
+fn test_division_by_zero() {
+    for code in [
+        "(/ 1 0)",
+        "(/ 0)",
+        "(/ 1.5 0.0)",
+        "(mod 5 0)",
+        "(rem 5 0)",
+        "(floor 5 0)",
+        "(expt 0 -1)",
+        "(expt 0.0 -0.5)",
+    ] {
+        assert!(
+            matches!(eval(code), Err(ArrowError::Arithmetic { .. })),
+            "{}",
+            code
+        );
+    }
+}
+
+#[test]
+fn test_numeric_functions() {
+    assert_eq!(show("(mod -7 2)"), "1");
+    assert_eq!(show("(rem -7 2)"), "-1");
+    assert_eq!(show("(quotient 7 2)"), "3");
+    assert_eq!(show("(abs -3)"), "3");
+    assert_eq!(show("(min 3 1 2)"), "1");
+    assert_eq!(show("(max 3 1 2.0)"), "3.0");
+    assert_eq!(show("(expt 2 10)"), "1024");
+    assert_eq!(show("(expt 2 -1)"), "0.5");
+    assert_eq!(show("(sqrt 16)"), "4.0");
+    assert_eq!(show("(floor -2.5)"), "-3");
+    assert_eq!(show("(ceiling 2.1)"), "3");
+    assert_eq!(show("(round 2.5)"), "2");
+    assert_eq!(show("(round 3.5)"), "4");
+    assert_eq!(show("(truncate -2.7)"), "-2");
+    assert_eq!(show("(floor 7 2)"), "3");
+    assert!(eval("(sqrt -1)").is_err());
+}
+
+#[test]
+fn test_integer_float_contagion() {
+    assert_eq!(show("(+ 1 2)"), "3");
+    assert_eq!(show("(+ 1 2.0)"), "3.0");
+    assert_eq!(show("(* 2 1.5)"), "3.0");
+    assert_eq!(show("(/ 7 2)"), "3");
+    assert_eq!(show("(/ 7 2.0)"), "3.5");
+    assert_eq!(show("(/ 5 2 2.0)"), "1.25");
+}
+
+#[test]
+fn test_number_literals() {
+    assert!(matches!(eval("(+ 12)").unwrap(), LispType::Integer(12)));
+    assert!(matches!(eval("(+ 12.0)").unwrap(), LispType::Float(f) if f == 12.));
+    assert_eq!(show("(+ 12.)"), "12");
+    assert_eq!(show("(+ #x1F #o17 #b101)"), "51");
+    assert_eq!(show("(- #x-10)"), "16");
+    assert_eq!(show("(+ 1e3)"), "1000.0");
+}
+
+#[test]
+fn test_integer_overflow() {
+    let err = eval("(* 99999999999 99999999999)").unwrap_err();
+    assert!(matches!(err, ArrowError::Arithmetic { .. }));
+    assert_eq!(err.to_string(), "arithmetic error: integer overflow at 1:1");
+    assert!(eval("(+ 9223372036854775807 1)").is_err());
+    assert!(matches!(
+        ast("(+ 99999999999999999999 1)"),
+        Err(ArrowError::Parse { .. })
+    ));
+    assert_eq!(show("(* 99999999999.0 99999999999)"), "9.9999999998e21");
+}
+
+#[test]
+fn test_bitwise_functions() {
+    assert_eq!(show("(logand 12 10)"), "8");
+    assert_eq!(show("(logior 12 10)"), "14");
+    assert_eq!(show("(logxor 12 10)"), "6");
+    assert_eq!(show("(logand)"), "-1");
+    assert_eq!(show("(lognot 5)"), "-6");
+    assert_eq!(show("(ash 1 10)"), "1024");
+    assert_eq!(show("(ash -8 -1)"), "-4");
+    assert_eq!(show("(ash 0 64)"), "0");
+    assert_eq!(show("(ash 5 -64)"), "0");
+    assert!(matches!(
+        eval("(ash 1 64)"),
+        Err(ArrowError::Arithmetic { .. })
+    ));
+    assert!(matches!(
+        eval("(logand 1.0 3)"),
+        Err(ArrowError::Type { .. })
+    ));
+}
@@ -0,0 +1,120 @@
+mod common;
+
+use arrow::error::ArrowError;
+use arrow::lisptype::LispType;
+use arrow::tokenize::{ast, create_lisptypes, ChildrenType};
+use common::eval;
+
+#[test]
+fn test_funcall_lambda() {
+    assert_eq!(
+        eval("(funcall (lambda (a b) (* a b)) 6 7)").unwrap(),
+        LispType::Integer(42)
+    );
+    assert_eq!(
+        eval("(funcall (lambda () 1 2))").unwrap(),
+        LispType::Integer(2)
+    );
+    assert_eq!(
+        eval("(funcall #'(lambda (a &optional b) (if b b a)) 1)").unwrap(),
+        LispType::Integer(1)
+    );
+    assert_eq!(
+        eval("(funcall (function (lambda (x) x)) 'a)").unwrap(),
+        LispType::Symbol("a".to_string())
+    );
+}
+
+#[test]
+fn test_closures_capture_their_scope() {
+    let code = "(defun adder (n) (lambda (x) (+ x n)))
+                (let ((add2 (adder 2)) (n 100))
+                  (funcall add2 5))";
+    assert_eq!(eval(code).unwrap(), LispType::Integer(7));
+    let code = "(let ((f (let ((x 1)) (lambda () x)))) (funcall f))";
+    assert_eq!(eval(code).unwrap(), LispType::Integer(1));
+}
+
+#[test]
+fn test_funcall_by_name() {
+    let code = "(defun twice (f x) (funcall f (funcall f x)))
+                (defun inc (x) (+ x 1))
+                (twice #'inc 5)";
+    assert_eq!(eval(code).unwrap(), LispType::Integer(7));
+    assert_eq!(eval("(funcall '+ 1 2 3)").unwrap(), LispType::Integer(6));
+    assert_eq!(eval("(funcall #'max 1 3 2)").unwrap(), LispType::Integer(3));
+    assert!(matches!(
+        eval("(funcall 'missing 1)"),
+        Err(ArrowError::UnknownFunction { .. })
+    ));
+    assert!(matches!(
+        eval("(funcall 'if t 1)"),
+        Err(ArrowError::InvalidArgument { .. })
+    ));
+    assert!(matches!(
+        eval("(funcall 1 2)"),
+        Err(ArrowError::Type { .. })
+    ));
+}
+
+#[test]
+fn test_apply() {
+    assert_eq!(eval("(apply #'+ 1 2 nil)").unwrap(), LispType::Integer(3));
+    assert_eq!(
+        eval("(apply (lambda (&rest r) r) nil)").unwrap(),
+        LispType::Bool(false)
+    );
+    assert!(matches!(
+        eval("(apply #'+ 1 2)"),
+        Err(ArrowError::Type { .. })
+    ));
+}
+
+#[test]
+fn test_lambda_arity() {
+    assert!(matches!(
+        eval("(funcall (lambda (a) a))"),
+        Err(ArrowError::Arity { .. })
+    ));
+    assert!(ast("(lambda x 1)").and_then(create_lisptypes).is_err());
+    assert!(ast("(lambda (&rest) 1)")
+        .and_then(create_lisptypes)
+        .is_err());
+}
+
+#[test]
+fn test_closure_values() {
+    let code = "(let ((f (lambda () 1))) (eq f f))";
+    assert_eq!(eval(code).unwrap(), LispType::Bool(true));
+    let code = "(eq (lambda () 1) (lambda () 1))";
+    assert_eq!(eval(code).unwrap(), LispType::Bool(false));
+    let closure = eval("(lambda () 1)").unwrap();
+    assert_eq!(closure.type_name(), "function");
+    assert_eq!(closure.to_string().unwrap(), "#<function lambda>");
+    assert_eq!(
+        eval("(list 1 (lambda (x) x))")
+            .unwrap()
+            .to_readable()
+            .unwrap(),
+        "(1 #<function lambda>)"
+    );
+    assert!(eval("(quote #<function lambda>)").is_err());
+}
+
+#[test]
+fn test_function_quote_syntax() {
+    assert!(matches!(
+        &ast("#'f").unwrap()[0],
+        ChildrenType::Container(c) if c.name == "function"
+    ));
+    assert!(matches!(
+        &ast("(funcall #'f)").unwrap()[0],
+        ChildrenType::Container(c) if c.children.len() == 1
+    ));
+    assert!(ast("(funcall #')").is_err());
+    assert!(ast("#'").is_err());
+    assert_eq!(
+        eval("(function f)").unwrap(),
+        LispType::Symbol("f".to_string())
+    );
+}
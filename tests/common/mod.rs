@@ -0,0 +1,18 @@
+//! Helpers shared by the integration tests.
+
+use arrow::error::ArrowError;
+use arrow::lisptype::LispType;
+use arrow::Arrow;
+
+/// Evaluate all forms like [Arrow::eval] does, in a new interpreter,
+/// and return the last result.
+#[allow(dead_code)]
+pub fn eval(code: &str) -> Result<LispType, ArrowError> {
+    Arrow::default().eval(code)
+}
+
+/// Evaluate the code and print the result.
+#[allow(dead_code)]
+pub fn print(code: &str) -> String {
+    eval(code).unwrap().to_string().unwrap()
+}
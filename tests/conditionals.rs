@@ -0,0 +1,71 @@
+mod common;
+
+use arrow::error::ArrowError;
+use arrow::tokenize::{ast, create_lisptypes};
+use common::eval;
+
+fn show(code: &str) -> String {
+    eval(code).unwrap().to_string().unwrap()
+}
+
+#[test]
+fn test_if() {
+    assert_eq!(show("(if (< 1 2) 1 2)"), "1");
+    assert_eq!(show("(if (> 1 2) 1 2)"), "2");
+    assert_eq!(show("(if nil 1)"), "nil");
+    assert_eq!(show("(if nil 1 2 3)"), "3");
+    assert_eq!(show("(if 0 1 2)"), "1");
+}
+
+#[test]
+fn test_only_the_taken_branch_runs() {
+    assert_eq!(show("(if t 1 (/ 1 0))"), "1");
+    assert_eq!(show("(if nil (/ 1 0) 2)"), "2");
+    assert_eq!(show("(when nil (/ 1 0))"), "nil");
+    assert_eq!(show("(unless t (/ 1 0))"), "nil");
+    assert_eq!(show("(cond (t 1) ((/ 1 0) 2))"), "1");
+    assert!(eval("(if t (/ 1 0) 2)").is_err());
+}
+
+#[test]
+fn test_when_and_unless() {
+    assert_eq!(show("(when (= 1 1) 1 2)"), "2");
+    assert_eq!(show("(unless (= 1 1) 1 2)"), "nil");
+    assert_eq!(show("(unless nil 1 2)"), "2");
+}
+
+#[test]
+fn test_cond() {
+    let code = |n: i64| format!("(cond ((< {} 0) -1) ((= {} 0) 0) (t 1))", n, n);
+    assert_eq!(show(&code(-5)), "-1");
+    assert_eq!(show(&code(0)), "0");
+    assert_eq!(show(&code(5)), "1");
+    assert_eq!(show("(cond (nil 1))"), "nil");
+    assert_eq!(show("(cond)"), "nil");
+    assert_eq!(show("(cond (nil) ((+ 1 2)))"), "3");
+}
+
+#[test]
+fn test_case() {
+    let code = |n: &str| format!("(case {} (1 \"one\") ((2 3) \"few\") (t \"many\"))", n);
+    assert_eq!(show(&code("1")), "one");
+    assert_eq!(show(&code("(+ 1 2)")), "few");
+    assert_eq!(show(&code("9")), "many");
+    assert_eq!(show("(case 'a ('a 1) (otherwise 2))"), "1");
+    assert_eq!(show("(case 1.0 (1 1))"), "nil");
+}
+
+#[test]
+fn test_malformed_clauses() {
+    for code in ["(cond 1)", "(cond ())", "(case 1 ((2 (3)) 4))"] {
+        assert!(
+            matches!(
+                ast(code).and_then(create_lisptypes),
+                Err(ArrowError::Parse { .. })
+            ),
+            "{}",
+            code
+        );
+    }
+    assert!(matches!(eval("(if t)"), Err(ArrowError::Arity { .. })));
+}
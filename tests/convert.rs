@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+use arrow::convert::{FromLisp, IntoLisp};
+use arrow::error::ArrowError;
+use arrow::lisptype::LispType;
+use arrow::Arrow;
+
+#[test]
+fn test_call_with_rust_values() {
+    let mut arrow = Arrow::default()
+        .add_function("(defun calc (a b) (+ a b))")
+        .unwrap()
+        .add_function("(defun describe (name tags) (list name (length tags) (car tags)))")
+        .unwrap();
+    let n: i64 = arrow.call("calc", (1, 2)).unwrap().into_rust().unwrap();
+    assert_eq!(n, 3);
+    let f: f64 = arrow.call("calc", (1, 0.5)).unwrap().into_rust().unwrap();
+    assert_eq!(f, 1.5);
+
+    let res: (String, usize, Option<String>) = arrow
+        .call("describe", ("arrow", vec!["lisp", "rust"]))
+        .unwrap()
+        .into_rust()
+        .unwrap();
+    assert_eq!(res, ("arrow".to_string(), 2, Some("lisp".to_string())));
+    let res: (String, usize, Option<String>) = arrow
+        .call("describe", ("empty", Vec::<String>::new()))
+        .unwrap()
+        .into_rust()
+        .unwrap();
+    assert_eq!(res.2, None);
+}
+
+#[test]
+fn test_hash_maps() {
+    let mut scores = HashMap::new();
+    scores.insert("ada".to_string(), 3);
+    scores.insert("bob".to_string(), 5);
+
+    let mut arrow = Arrow::default();
+    arrow
+        .environment()
+        .define("scores", scores.clone().into_lisp());
+    assert_eq!(
+        arrow.eval("(gethash \"bob\" scores)").unwrap(),
+        LispType::Integer(5)
+    );
+    arrow.eval("(puthash \"cy\" 1 scores)").unwrap();
+
+    let back: HashMap<String, i64> = arrow.eval("scores").unwrap().into_rust().unwrap();
+    scores.insert("cy".to_string(), 1);
+    assert_eq!(back, scores);
+
+    assert!(arrow
+        .eval("#s(hash-table data (a 1))")
+        .unwrap()
+        .into_rust::<HashMap<String, i64>>()
+        .is_err());
+}
+
+#[test]
+fn test_conversion_errors() {
+    let mut arrow = Arrow::default();
+    let value = arrow.eval("'(1 \"a\")").unwrap();
+    assert!(matches!(
+        value.clone().into_rust::<Vec<i64>>(),
+        Err(ArrowError::Type { .. })
+    ));
+    assert!(matches!(
+        value.clone().into_rust::<(i64,)>(),
+        Err(ArrowError::InvalidArgument { .. })
+    ));
+    assert!(value.into_rust::<(i64, String)>().is_ok());
+    let v: Vec<bool> = arrow.eval("[t nil 0]").unwrap().into_rust().unwrap();
+    assert_eq!(v, vec![true, false, true]);
+    assert!(Vec::<i64>::from_lisp(LispType::Integer(1)).is_err());
+}
+
+#[test]
+fn test_typed_native_functions() {
+    let mut arrow = Arrow::default()
+        .register_fn("add", |a: i64, b: i64| Ok(a + b))
+        .unwrap()
+        .register_fn("greet", |name: Option<String>| {
+            Ok(format!("hello {}", name.as_deref().unwrap_or("world")))
+        })
+        .unwrap()
+        .register_fn("nothing", || Ok(()))
+        .unwrap()
+        .register_fn("fail", |message: String| -> Result<(), _> {
+            Err(ArrowError::invalid(message))
+        })
+        .unwrap()
+        .register_fn("count", |s: String| Ok(s.chars().count()))
+        .unwrap();
+
+    assert_eq!(
+        arrow.eval("(add 2 (add 3 4))").unwrap(),
+        LispType::Integer(9)
+    );
+    assert_eq!(
+        arrow
+            .eval("(list (greet \"arrow\") (greet nil))")
+            .unwrap()
+            .into_rust::<Vec<String>>()
+            .unwrap(),
+        vec!["hello arrow", "hello world"]
+    );
+    assert_eq!(arrow.eval("(nothing)").unwrap(), LispType::Bool(false));
+    assert!(matches!(
+        arrow.eval("(add 1)"),
+        Err(ArrowError::Arity { .. })
+    ));
+    assert!(matches!(
+        arrow.eval("(add 1 \"2\")"),
+        Err(ArrowError::Type { .. })
+    ));
+    assert_eq!(
+        arrow.eval("(count \"arrow\")").unwrap(),
+        LispType::Integer(5)
+    );
+    match arrow.eval("(fail \"broken\")") {
+        Err(ArrowError::InvalidArgument { message, .. }) => assert_eq!(message, "broken"),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn test_large_integers() {
+    assert_eq!(7usize.into_lisp(), LispType::Integer(7));
+    assert_eq!((-7isize).into_lisp(), LispType::Integer(-7));
+    assert_eq!(u64::MAX.into_lisp(), LispType::Integer(i64::MAX));
+    assert_eq!(usize::MAX.into_lisp(), LispType::Integer(i64::MAX));
+    assert_eq!(isize::MIN.into_lisp(), LispType::Integer(i64::MIN));
+}
@@ -0,0 +1,241 @@
+mod common;
+
+use arrow::error::ArrowError;
+use arrow::expression::Arity;
+use arrow::lisptype::LispType;
+use arrow::list;
+use arrow::tokenize::{ast, create_lisptypes};
+use arrow::Arrow;
+use common::eval;
+
+#[test]
+fn test_call_with_arguments() {
+    let code = "(defun add (a b) (+ a b)) (add 2 3)";
+    assert_eq!(eval(code).unwrap(), LispType::Integer(5));
+    let code = "(defun f () 1 2) (f)";
+    assert_eq!(eval(code).unwrap(), LispType::Integer(2));
+    assert_eq!(
+        eval("(defun f () 1)").unwrap(),
+        LispType::Symbol("f".to_string())
+    );
+}
+
+#[test]
+fn test_optional_parameters() {
+    let code = "(defun f (a &optional b) (if b (+ a b) a))";
+    assert_eq!(
+        eval(&format!("{} (f 1)", code)).unwrap(),
+        LispType::Integer(1)
+    );
+    assert_eq!(
+        eval(&format!("{} (f 1 2)", code)).unwrap(),
+        LispType::Integer(3)
+    );
+}
+
+#[test]
+fn test_rest_parameter() {
+    let code = "(defun f (a &rest more) (if more 1 a)) (f 5)";
+    assert_eq!(eval(code).unwrap(), LispType::Integer(5));
+}
+
+#[test]
+fn test_wrong_number_of_arguments() {
+    let err = eval("(defun f (a &optional b) a) (f 1 2 3)").unwrap_err();
+    match &err {
+        ArrowError::Arity {
+            name,
+            expected,
+            found,
+            span,
+        } => {
+            assert_eq!(name, "f");
+            assert_eq!(*expected, Arity::Range(1, 2));
+            assert_eq!(*found, 3);
+            assert_eq!(span.unwrap().start.column, 29);
+        }
+        e => panic!("unexpected error: {:?}", e),
+    }
+    assert!(matches!(
+        eval("(defun f (a) a) (f)"),
+        Err(ArrowError::Arity { .. })
+    ));
+}
+
+#[test]
+fn test_functions_use_a_fresh_scope() {
+    let code = "(defun f () x) (let ((x 1)) (f))";
+    assert!(matches!(
+        eval(code),
+        Err(ArrowError::UnboundVariable { .. })
+    ));
+    let code = "(defun f (x) x) (let ((x 1)) (+ (f 2) x))";
+    assert_eq!(eval(code).unwrap(), LispType::Integer(3));
+}
+
+#[test]
+fn test_functions_call_each_other() {
+    let code = "(defun double (x) (* 2 x))
+                (defun quad (x) (double (double x)))
+                (quad 3)";
+    assert_eq!(eval(code).unwrap(), LispType::Integer(12));
+}
+
+#[test]
+fn test_recursion() {
+    let code = "(defun fact (n) (if (<= n 1) 1 (* n (fact (- n 1)))))
+                (fact 10)";
+    assert_eq!(eval(code).unwrap(), LispType::Integer(3628800));
+    let code = "(defun count (n) (if (= n 0) 0 (+ 1 (count (- n 1)))))";
+    assert_eq!(
+        eval(&format!("{} (count 30)", code)).unwrap(),
+        LispType::Integer(30)
+    );
+    let err = eval(&format!("{} (count 100000)", code)).unwrap_err();
+    assert!(matches!(err, ArrowError::Depth { .. }));
+    // The span is the one of the expression, that ran out of stack.
+    assert!(err
+        .to_string()
+        .starts_with("expressions are nested too deep at 1:"));
+}
+
+#[test]
+fn test_endless_recursion() {
+    for code in [
+        "(defun f () (f)) (f)",
+        "(defun f () (funcall (lambda () (f)))) (f)",
+        "(defun f (n) (apply #'f (list n))) (f 1)",
+        "(defun f () (let ((x 1)) (cond (t (when x (f)))))) (f)",
+        "(defun f () (let* ((x (f))) x)) (f)",
+    ] {
+        assert!(
+            matches!(eval(code), Err(ArrowError::Depth { .. })),
+            "{}",
+            code
+        );
+    }
+}
+
+#[test]
+fn test_unknown_function() {
+    let err = eval("(+ 1 (foo 2))").unwrap_err();
+    assert_eq!(err.to_string(), "unknown function `foo` at 1:6");
+}
+
+#[test]
+fn test_malformed_defun() {
+    for code in [
+        "(defun 'f () 1)",
+        "(defun f 1)",
+        "(defun (f) () 1)",
+        "(defun f (a (b)) 1)",
+        "(defun f (&rest) 1)",
+        "(defun f (&rest a b) 1)",
+        "(defun f ('a) 1)",
+    ] {
+        assert!(ast(code).and_then(create_lisptypes).is_err(), "{}", code);
+    }
+}
+
+#[test]
+fn test_builtin_names_cant_be_redefined() {
+    for code in [
+        "(defun car (x) 42)",
+        "(defmacro if (c a b) a)",
+        "(defun setq () 1)",
+    ] {
+        let err = eval(code).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("is a builtin function and can't be replaced"),
+            "{}",
+            code
+        );
+    }
+    let mut arrow = Arrow::default();
+    assert!(arrow.eval("(defun car (x) 42)").is_err());
+    let code = "(list (car '(1)) (funcall 'car '(1)) (funcall #'car '(1)))";
+    assert_eq!(arrow.eval(code).unwrap().to_string().unwrap(), "(1 1 1)");
+    assert!(arrow.functions().is_empty());
+}
+
+#[test]
+fn test_internal_forms_cant_be_named() {
+    for (code, name) in [
+        ("(defun f (x) x) (#invoke f 1)", "#invoke"),
+        ("(defun f (x) x) (#call f '(1) 1)", "#call"),
+    ] {
+        assert!(
+            matches!(eval(code), Err(ArrowError::UnknownFunction { name: n, .. }) if n == name),
+            "{}",
+            code
+        );
+    }
+    let one = |_: &[LispType]| Ok(LispType::Integer(1));
+    let mut arrow = Arrow::default()
+        .register_native("#call", Arity::Exact(0), one)
+        .unwrap();
+    assert_eq!(arrow.eval("(#call)").unwrap(), LispType::Integer(1));
+    assert_eq!(arrow.eval("(list 1)").unwrap().to_string().unwrap(), "(1)");
+}
+
+#[test]
+fn test_run_registered_function() {
+    let mut arrow = Arrow::default()
+        .add_function("(defun helper (x) (* x 2))")
+        .unwrap()
+        .add_function("(defun main () (helper 21))")
+        .unwrap();
+    assert_eq!(arrow.run("main").unwrap(), LispType::Integer(42));
+    assert!(matches!(
+        arrow.run("missing"),
+        Err(ArrowError::UnknownFunction { .. })
+    ));
+}
+
+#[test]
+fn test_call_registered_function() {
+    let mut arrow = Arrow::default()
+        .add_function("(defun pair (a b) (list a b))")
+        .unwrap()
+        .add_function("(defun positive (x) (> x 0))")
+        .unwrap();
+    let args = [
+        LispType::Symbol("a".to_string()),
+        list::list(vec![LispType::Integer(1)]),
+    ];
+    assert_eq!(
+        arrow.call("pair", &args).unwrap(),
+        list::list(args.to_vec())
+    );
+    assert_eq!(
+        arrow.call("positive", &[LispType::Float(0.5)]).unwrap(),
+        LispType::Bool(true)
+    );
+    assert!(matches!(
+        arrow.call("pair", &[LispType::Integer(1)]),
+        Err(ArrowError::Arity { .. })
+    ));
+    assert!(matches!(
+        arrow.call("missing", &[]),
+        Err(ArrowError::UnknownFunction { .. })
+    ));
+}
+
+#[test]
+fn test_registry() {
+    let arrow = Arrow::default()
+        .add_function("(defun f () 1) (defun g () 2)")
+        .unwrap();
+    assert_eq!(arrow.functions(), vec!["f", "g"]);
+
+    let mut arrow = arrow.add_function("(defun f () 3)").unwrap();
+    assert_eq!(arrow.run("f").unwrap(), LispType::Integer(3));
+    assert_eq!(arrow.run("g").unwrap(), LispType::Integer(2));
+    assert_eq!(arrow.functions(), vec!["f", "g"]);
+
+    assert!(arrow.remove_function("f"));
+    assert!(!arrow.remove_function("f"));
+    assert_eq!(arrow.functions(), vec!["g"]);
+    assert!(arrow.run("f").is_err());
+}
@@ -0,0 +1,161 @@
+mod common;
+
+use arrow::error::ArrowError;
+use arrow::lisptype::LispType;
+use arrow::Arrow;
+use common::eval;
+
+#[test]
+fn test_defvar() {
+    assert_eq!(
+        eval("(defvar x 1 \"doc\") (progn x)").unwrap(),
+        LispType::Integer(1)
+    );
+    assert_eq!(
+        eval("(defvar x 1) (defvar x 2) (progn x)").unwrap(),
+        LispType::Integer(1)
+    );
+    assert_eq!(
+        eval("(defvar x)").unwrap(),
+        LispType::Symbol("x".to_string())
+    );
+    assert!(matches!(
+        eval("(defvar x) (progn x)"),
+        Err(ArrowError::UnboundVariable { .. })
+    ));
+    assert_eq!(
+        eval("(let ((y 1)) (defvar x y)) (progn x)").unwrap(),
+        LispType::Integer(1)
+    );
+}
+
+#[test]
+fn test_defparameter() {
+    assert_eq!(
+        eval("(defparameter x 1) (defparameter x 2) (progn x)").unwrap(),
+        LispType::Integer(2)
+    );
+}
+
+#[test]
+fn test_defconst() {
+    assert_eq!(
+        eval("(defconst c 3) (* c c)").unwrap(),
+        LispType::Integer(9)
+    );
+    for code in [
+        "(defconst c 3) (setq c 4)",
+        "(defconst c 3) (setf c 4)",
+        "(defconst c 3) (defparameter c 4)",
+        "(defconst c 3) (let ((x 1)) (setq c x))",
+    ] {
+        assert!(
+            matches!(eval(code), Err(ArrowError::InvalidArgument { .. })),
+            "{}",
+            code
+        );
+    }
+    assert_eq!(
+        eval("(defconst c 3) (let ((c 1)) (setq c 2) c)").unwrap(),
+        LispType::Integer(2)
+    );
+}
+
+#[test]
+fn test_setq() {
+    assert_eq!(eval("(setq a 1 b (+ a 1))").unwrap(), LispType::Integer(2));
+    assert_eq!(eval("(setq)").unwrap(), LispType::Bool(false));
+    assert_eq!(
+        eval("(setq x 1) (let ((x 10)) (setq x 20)) (progn x)").unwrap(),
+        LispType::Integer(1)
+    );
+    assert_eq!(
+        eval("(let ((n 0)) (dotimes (i 4) (setq n (+ n i))) n)").unwrap(),
+        LispType::Integer(6)
+    );
+    assert!(matches!(
+        eval("(setq a)"),
+        Err(ArrowError::InvalidArgument { .. })
+    ));
+    assert!(matches!(eval("(setq 1 2)"), Err(ArrowError::Type { .. })));
+}
+
+#[test]
+fn test_setf() {
+    assert_eq!(eval("(setf a 1) (+ a 1)").unwrap(), LispType::Integer(2));
+    assert!(matches!(
+        eval("(setf (+ 1 2) 1)"),
+        Err(ArrowError::InvalidArgument { .. })
+    ));
+}
+
+#[test]
+fn test_setf_places() {
+    let print = |code| eval(code).unwrap().to_readable().unwrap();
+    assert_eq!(print("(setq l (list 1 2 3)) (setf (car l) 0) l"), "(0 2 3)");
+    assert_eq!(
+        print("(setq l (list 1 2 3)) (setf (cdr l) '(5)) l"),
+        "(1 5)"
+    );
+    assert_eq!(
+        print("(setq l (list 1 2 3)) (setf (car (cdr (cdr l))) 'c) l"),
+        "(1 2 c)"
+    );
+    assert_eq!(
+        print("(setq v (vector 1 2)) (setf (aref v 1) 'b) v"),
+        "[1 b]"
+    );
+    assert_eq!(
+        print("(setq h (make-hash-table)) (setf (gethash 'a h) 1) (gethash 'a h)"),
+        "1"
+    );
+    assert_eq!(
+        print("(let ((l (list (list 1)))) (push 0 (car l)) (pop (cdr l)) l)"),
+        "((0 1))"
+    );
+    // Cons cells aren't changed, only the place gets a new list.
+    assert_eq!(
+        print("(setq a (list 1 2)) (setq b a) (setf (car a) 0) (list a b)"),
+        "((0 2) (1 2))"
+    );
+    assert!(matches!(
+        eval("(setq l nil) (setf (car l) 1)"),
+        Err(ArrowError::Type { .. })
+    ));
+    assert!(matches!(
+        eval("(setf (aref (vector 1) 5) 1)"),
+        Err(ArrowError::InvalidArgument { .. })
+    ));
+    assert!(matches!(
+        eval("(setf (car (list 1)) 2)"),
+        Err(ArrowError::InvalidArgument { .. })
+    ));
+}
+
+#[test]
+fn test_closures_share_state() {
+    let code = "(defun make-counter ()
+                  (let ((n 0)) (lambda () (setq n (+ n 1)))))
+                (let ((c (make-counter)))
+                  (funcall c)
+                  (funcall c))";
+    assert_eq!(eval(code).unwrap(), LispType::Integer(2));
+}
+
+#[test]
+fn test_globals_persist_between_runs() {
+    let mut arrow = Arrow::default()
+        .add_function("(defvar total 0)")
+        .unwrap()
+        .add_function("(defun add-ten () (setq total (+ total 10)))")
+        .unwrap();
+    assert_eq!(arrow.run("add-ten").unwrap(), LispType::Integer(10));
+    assert_eq!(arrow.run("add-ten").unwrap(), LispType::Integer(20));
+
+    let mut arrow = arrow.add_function("(defun get-total () total)").unwrap();
+    assert_eq!(arrow.run("get-total").unwrap(), LispType::Integer(20));
+    assert_eq!(
+        arrow.environment().get("total"),
+        Some(LispType::Integer(20))
+    );
+}
@@ -0,0 +1,136 @@
+mod common;
+
+use arrow::error::ArrowError;
+use arrow::lisptype::LispType;
+use arrow::tokenize::{ast, create_lisptypes};
+use common::{eval, print};
+
+#[test]
+fn test_gethash_and_puthash() {
+    let code = "(setq h (make-hash-table))
+                (puthash 'a 1 h)
+                (puthash 'b 2 h)
+                (puthash 'a 3 h)
+                (list (gethash 'a h) (gethash 'b h) (gethash 'c h)
+                      (gethash 'c h 'none) (hash-table-count h))";
+    assert_eq!(print(code), "(3 2 nil none 2)");
+    assert_eq!(
+        eval("(puthash 1 2 (make-hash-table))").unwrap(),
+        LispType::Integer(2)
+    );
+}
+
+#[test]
+fn test_remhash() {
+    let code = "(setq h (make-hash-table))
+                (puthash 1 'one h)
+                (puthash 2 'two h)
+                (puthash 3 'three h)
+                (remhash 2 h)
+                (remhash 4 h)
+                (list (hash-table-count h) (hash-table-keys h) (gethash 3 h))";
+    assert_eq!(print(code), "(2 (1 3) three)");
+}
+
+#[test]
+fn test_test_option() {
+    let code = "(setq eq-table (make-hash-table)
+                      equal-table (make-hash-table :test 'equal))
+                (puthash (list 1 2) 'found eq-table)
+                (puthash (list 1 2) 'found equal-table)
+                (list (gethash (list 1 2) eq-table) (gethash (list 1 2) equal-table))";
+    assert_eq!(print(code), "(nil found)");
+    assert_eq!(
+        print("(let ((h (make-hash-table :test #'eq :size 10))) (puthash \"s\" 1 h) (gethash \"s\" h))"),
+        "1"
+    );
+    for code in [
+        "(make-hash-table :test 'foo)",
+        "(make-hash-table :test)",
+        "(make-hash-table :weakness t)",
+        "(make-hash-table 'test 'equal)",
+    ] {
+        assert!(
+            matches!(eval(code), Err(ArrowError::InvalidArgument { .. })),
+            "{}",
+            code
+        );
+    }
+}
+
+#[test]
+fn test_maphash() {
+    let code = "(setq h (make-hash-table) sum 0)
+                (puthash 'a 1 h)
+                (puthash 'b 2 h)
+                (maphash (lambda (k v) (setq sum (+ sum v))) h)
+                (progn sum)";
+    assert_eq!(eval(code).unwrap(), LispType::Integer(3));
+    let code = "(setq h (make-hash-table))
+                (puthash 'a 1 h)
+                (puthash 'b 2 h)
+                (maphash (lambda (k v) (remhash k h) (puthash v k h)) h)
+                (hash-table-keys h)";
+    assert_eq!(print(code), "(1 2)");
+}
+
+#[test]
+fn test_tables_are_shared() {
+    let code = "(defun remember (table) (puthash 'seen t table))
+                (let ((h (make-hash-table)))
+                  (remember h)
+                  (list (gethash 'seen h) (eq h h) (equal h (make-hash-table))))";
+    assert_eq!(print(code), "(t t nil)");
+}
+
+#[test]
+fn test_print_and_read() {
+    let code = "(let ((h (make-hash-table :test 'equal)))
+                  (puthash 'a 1 h)
+                  (puthash '(b c) 2 h)
+                  h)";
+    let printed = print(code);
+    assert_eq!(printed, "#s(hash-table test equal data (a 1 (b c) 2))");
+    assert_eq!(print(&printed), printed);
+    assert_eq!(
+        print("(gethash '(b c) #s(hash-table test equal data (a 1 (b c) 2)))"),
+        "2"
+    );
+    assert_eq!(print("(make-hash-table)"), "#s(hash-table test eq data ())");
+    assert_eq!(
+        print("(hash-table-count (car '(#s(hash-table data (a 1 b 2)))))"),
+        "2"
+    );
+    assert_eq!(
+        print("(let ((x 1)) `(,x #s(hash-table data (k v))))"),
+        "(1 #s(hash-table test eq data (k v)))"
+    );
+}
+
+#[test]
+fn test_malformed_records() {
+    for code in [
+        "(progn #s(hash-table data (a)))",
+        "(progn #s(vector 1))",
+        "(progn #s(hash-table test foo))",
+        "(progn #s(hash-table data 5))",
+    ] {
+        assert!(ast(code).and_then(create_lisptypes).is_err(), "{}", code);
+    }
+}
+
+#[test]
+fn test_type_errors() {
+    for code in [
+        "(gethash 1 (list 1))",
+        "(puthash 1 2 nil)",
+        "(hash-table-count 5)",
+        "(let ((h (make-hash-table))) (puthash 1 1 h) (maphash 5 h))",
+    ] {
+        assert!(
+            matches!(eval(code), Err(ArrowError::Type { .. })),
+            "{}",
+            code
+        );
+    }
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use arrow::error::ArrowError;
+use arrow::lisptype::LispType;
+use common::{eval, print};
+
+#[test]
+fn test_cons_car_cdr() {
+    assert_eq!(print("(cons 1 (cons 2 nil))"), "(1 2)");
+    assert_eq!(print("(cons 1 2)"), "(1 . 2)");
+    assert_eq!(print("(cons 1 (cons 2 3))"), "(1 2 . 3)");
+    assert_eq!(print("(car (list 1 2))"), "1");
+    assert_eq!(print("(cdr (list 1 2))"), "(2)");
+    assert_eq!(print("(cdr (cons 1 2))"), "2");
+    assert_eq!(print("(car nil)"), "nil");
+    assert_eq!(print("(cdr nil)"), "nil");
+    assert!(matches!(eval("(car 1)"), Err(ArrowError::Type { .. })));
+}
+
+#[test]
+fn test_nil_is_the_empty_list() {
+    assert_eq!(eval("(list)").unwrap(), LispType::Bool(false));
+    assert_eq!(print("(cons nil nil)"), "(nil)");
+    assert_eq!(eval("(length nil)").unwrap(), LispType::Integer(0));
+    assert_eq!(eval("(if (list) 1 2)").unwrap(), LispType::Integer(2));
+    assert_eq!(eval("(if (list nil) 1 2)").unwrap(), LispType::Integer(1));
+}
+
+#[test]
+fn test_list_functions() {
+    assert_eq!(
+        print("(append (list 1 2) nil (list 3) (list 4))"),
+        "(1 2 3 4)"
+    );
+    assert_eq!(print("(append (list 1) 2)"), "(1 . 2)");
+    assert_eq!(print("(append)"), "nil");
+    assert_eq!(print("(reverse (list 1 2 3))"), "(3 2 1)");
+    assert_eq!(print("(length (list 1 (list 2 3)))"), "2");
+    assert_eq!(print("(nth 1 (list 'a 'b))"), "b");
+    assert_eq!(print("(nth 5 (list 'a 'b))"), "nil");
+    assert_eq!(print("(nthcdr 1 (list 1 2 3))"), "(2 3)");
+    assert_eq!(print("(last (list 1 2 3))"), "(3)");
+    assert_eq!(print("(last (list 1 2 3) 2)"), "(2 3)");
+    assert_eq!(print("(member 2 (list 1 2 3))"), "(2 3)");
+    assert_eq!(print("(member 4 (list 1 2 3))"), "nil");
+    assert_eq!(print("(member (list 1) (list (list 1) 2))"), "((1) 2)");
+    assert_eq!(
+        print("(assoc 'b (list (cons 'a 1) 2 (cons 'b 2)))"),
+        "(b . 2)"
+    );
+    assert_eq!(print("(assoc 'c (list (cons 'a 1)))"), "nil");
+    assert!(eval("(length (cons 1 2))").is_err());
+    assert!(eval("(reverse 1)").is_err());
+}
+
+#[test]
+fn test_list_equality() {
+    assert_eq!(
+        eval("(equal (list 1 (list 2)) (list 1 (list 2)))").unwrap(),
+        LispType::Bool(true)
+    );
+    assert_eq!(
+        eval("(equal (list 1) (list 1.0))").unwrap(),
+        LispType::Bool(false)
+    );
+    assert_eq!(
+        eval("(eq (list 1) (list 1))").unwrap(),
+        LispType::Bool(false)
+    );
+    assert_eq!(
+        eval("(let ((l (list 1))) (eq l l))").unwrap(),
+        LispType::Bool(true)
+    );
+}
+
+#[test]
+fn test_push_and_pop() {
+    let code = "(let ((stack nil))
+                  (push 1 stack)
+                  (push 2 stack)
+                  (list (pop stack) stack))";
+    assert_eq!(print(code), "(2 (1))");
+    assert_eq!(print("(let ((s nil)) (pop s))"), "nil");
+    assert!(eval("(let ((s 1)) (pop s))").is_err());
+}
+
+#[test]
+fn test_lists_in_loops_and_functions() {
+    let code = "(let ((sum 0)) (dolist (x (list 1 2 3) sum) (setq sum (+ sum x))))";
+    assert_eq!(eval(code).unwrap(), LispType::Integer(6));
+    assert!(eval("(dolist (x (cons 1 2)) x)").is_err());
+    assert_eq!(print("(defun f (a &rest r) r) (f 1 2 3)"), "(2 3)");
+    assert_eq!(print("(apply #'+ 1 (list 2 3))"), "6");
+    assert_eq!(print("(apply #'list 1 (list 2 3))"), "(1 2 3)");
+}
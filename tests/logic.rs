@@ -0,0 +1,75 @@
+mod common;
+
+use arrow::error::ArrowError;
+use common::eval;
+
+fn truthy(code: &str) -> bool {
+    eval(code).unwrap().truthy()
+}
+
+#[test]
+fn test_numeric_comparison() {
+    assert!(truthy("(< 1 2 3)"));
+    assert!(!truthy("(< 1 3 2)"));
+    assert!(truthy("(> 3 2.5 1)"));
+    assert!(truthy("(<= 1 1 2)"));
+    assert!(!truthy("(>= 1 2)"));
+    assert!(truthy("(= 1 1.0 1)"));
+    assert!(truthy("(/= 1 2)"));
+    assert!(!truthy("(/= 1 1.0)"));
+    assert!(truthy("(< 5)"));
+    assert!(matches!(eval("(< 1 \"2\")"), Err(ArrowError::Type { .. })));
+}
+
+#[test]
+fn test_equality() {
+    assert!(!truthy("(equal 1 \"1\")"));
+    assert!(!truthy("(equal 1 1.0)"));
+    assert!(truthy("(equal 1 1)"));
+    assert!(truthy("(equal \"a\" \"a\")"));
+    assert!(truthy("(eq 'a 'a)"));
+    assert!(!truthy("(eq 'a 'b)"));
+    assert!(truthy("(eql 1.5 1.5)"));
+    assert!(!truthy("(eql 0.0 -0.0)"));
+    assert!(truthy("(eq nil nil)"));
+}
+
+#[test]
+fn test_eq_and_eql_are_the_same() {
+    for (a, b) in [
+        ("1", "1"),
+        ("1", "1.0"),
+        ("1.5", "1.5"),
+        ("0.0", "-0.0"),
+        ("\"a\"", "\"a\""),
+        ("'a", "'a"),
+        ("(list 1)", "(list 1)"),
+        ("[1]", "[1]"),
+    ] {
+        let eq = truthy(&format!("(eq {} {})", a, b));
+        let eql = truthy(&format!("(eql {} {})", a, b));
+        assert_eq!(eq, eql, "{} {}", a, b);
+    }
+    assert!(truthy("(eq 1.0 1.0)"));
+    assert!(truthy("(eq \"a\" \"a\")"));
+    assert!(!truthy("(eq (list 1) (list 1))"));
+}
+
+#[test]
+fn test_boolean_logic() {
+    assert!(truthy("(and)"));
+    assert!(!truthy("(or)"));
+    assert!(truthy("(not nil)"));
+    assert!(!truthy("(not 0)"));
+    assert!(!truthy("(and t nil)"));
+    assert!(truthy("(or nil t)"));
+    assert_eq!(eval("(and 1 2 3)").unwrap().to_string().unwrap(), "3");
+    assert_eq!(eval("(or nil 2 3)").unwrap().to_string().unwrap(), "2");
+}
+
+#[test]
+fn test_short_circuit() {
+    assert!(!truthy("(and nil (/ 1 0))"));
+    assert!(truthy("(or t (/ 1 0))"));
+    assert!(eval("(and t (/ 1 0))").is_err());
+}
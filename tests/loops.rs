@@ -0,0 +1,130 @@
+mod common;
+
+use arrow::error::ArrowError;
+use arrow::expression::Arity;
+use arrow::lisptype::LispType;
+use arrow::tokenize::{ast, create_lisptypes};
+use arrow::Arrow;
+use common::eval;
+
+#[test]
+fn test_while() {
+    assert_eq!(eval("(while nil (/ 1 0))").unwrap(), LispType::Bool(false));
+    assert_eq!(eval("(while t (return 5))").unwrap(), LispType::Integer(5));
+    assert_eq!(eval("(while t (break))").unwrap(), LispType::Bool(false));
+}
+
+#[test]
+fn test_dotimes() {
+    assert_eq!(
+        eval("(dotimes (i 5 (+ i 0)))").unwrap(),
+        LispType::Integer(5)
+    );
+    assert_eq!(
+        eval("(dotimes (i 10) (when (= i 3) (return (* i 2))))").unwrap(),
+        LispType::Integer(6)
+    );
+    assert_eq!(
+        eval("(dotimes (i -1 7) (/ 1 0))").unwrap(),
+        LispType::Integer(7)
+    );
+    assert!(matches!(
+        eval("(dotimes (i 1.5))"),
+        Err(ArrowError::Type { .. })
+    ));
+}
+
+#[test]
+fn test_loop_variable_is_scoped() {
+    assert!(matches!(
+        eval("(progn (dotimes (i 3)) (+ i 1))"),
+        Err(ArrowError::UnboundVariable { .. })
+    ));
+    assert!(matches!(
+        eval("(progn (dotimes (i 3) (return 1)) (+ i 1))"),
+        Err(ArrowError::UnboundVariable { .. })
+    ));
+    assert_eq!(
+        eval("(dotimes (i 2 (+ i 0)) (dotimes (i 5)))").unwrap(),
+        LispType::Integer(2)
+    );
+}
+
+#[test]
+fn test_return_leaves_the_innermost_loop() {
+    assert_eq!(
+        eval("(loop (dotimes (i 3) (return 1)) (return 2))").unwrap(),
+        LispType::Integer(2)
+    );
+}
+
+#[test]
+fn test_dolist() {
+    assert_eq!(
+        eval("(dolist (x nil 7) (/ 1 0))").unwrap(),
+        LispType::Integer(7)
+    );
+    assert!(matches!(
+        eval("(dolist (x 5))"),
+        Err(ArrowError::Type { .. })
+    ));
+}
+
+#[test]
+fn test_malformed_loops() {
+    for code in [
+        "(dotimes)",
+        "(dotimes 5)",
+        "(dolist (x))",
+        "(dolist ((x) 1))",
+    ] {
+        assert!(
+            matches!(
+                ast(code).and_then(create_lisptypes),
+                Err(ArrowError::Parse { .. })
+            ),
+            "{}",
+            code
+        );
+    }
+}
+
+#[test]
+fn test_return_from_function() {
+    let mut arrow = Arrow::default()
+        .add_function("(defun main () (return 1) 2)")
+        .unwrap();
+    assert_eq!(arrow.run("main").unwrap(), LispType::Integer(1));
+
+    let err = eval("(return 1)").unwrap_err();
+    assert!(matches!(err, ArrowError::Return(_)));
+    assert_eq!(
+        err.to_string(),
+        "`return` used outside of a loop or function at 1:1"
+    );
+    assert!(matches!(
+        Arrow::default().eval("(break)"),
+        Err(ArrowError::Return(_))
+    ));
+    // Errors, that only look like a stray `return`, stay errors.
+    let mut arrow = Arrow::default()
+        .register_native("fail", Arity::Exact(0), |_| {
+            Err(ArrowError::invalid(
+                "`return` used outside of a loop or function",
+            ))
+        })
+        .unwrap();
+    assert!(matches!(
+        arrow.eval("(return 5)"),
+        Err(ArrowError::Return(_))
+    ));
+    assert!(matches!(
+        arrow.eval("(dotimes (i 2) (fail))"),
+        Err(ArrowError::InvalidArgument { .. })
+    ));
+    // The stray `return` doesn't turn later errors into values.
+    assert!(matches!(
+        arrow.eval("(defun f () (car 1)) (f)"),
+        Err(ArrowError::Type { .. })
+    ));
+}
@@ -0,0 +1,199 @@
+mod common;
+
+use arrow::environment::Environment;
+use arrow::error::ArrowError;
+use arrow::expression::Func;
+use arrow::lisptype::LispType;
+use arrow::macros::expand;
+use arrow::tokenize::{ast, create_lisptypes};
+use arrow::Arrow;
+use common::{eval, print};
+
+#[test]
+fn test_defmacro() {
+    let code = "(defmacro unless2 (c &rest body) `(if ,c nil (progn ,@body)))
+                (list (unless2 nil 1 2) (unless2 t 3))";
+    assert_eq!(print(code), "(2 nil)");
+    assert_eq!(
+        eval("(defmacro m () 1)").unwrap(),
+        LispType::Symbol("m".to_string())
+    );
+    assert!(matches!(
+        eval("(defmacro m (x) x) (m)"),
+        Err(ArrowError::Arity { .. })
+    ));
+}
+
+#[test]
+fn test_macros_get_unevaluated_arguments() {
+    let code = "(defmacro show (form) `(quote ,form)) (show (+ 1 undefined))";
+    assert_eq!(print(code), "(+ 1 undefined)");
+    let code = "(defmacro my-setq (var value) (list 'setq var value))
+                (my-setq x 5) (+ x 1)";
+    assert_eq!(eval(code).unwrap(), LispType::Integer(6));
+}
+
+#[test]
+fn test_small_dsl() {
+    let code = "(defmacro my-while (test &rest body)
+                  `(let ((continue t))
+                     (while (and continue ,test) ,@body)))
+                (defmacro inc (var &optional by)
+                  `(setq ,var (+ ,var ,(if by by 1))))
+                (let ((i 0) (sum 0))
+                  (my-while (< i 5) (inc sum i) (inc i))
+                  sum)";
+    assert_eq!(eval(code).unwrap(), LispType::Integer(10));
+}
+
+#[test]
+fn test_macroexpand() {
+    let code = "(defmacro inc (var) `(setq ,var (+ ,var 1)))
+                (defmacro inc2 (var) `(inc (inc ,var)))";
+    assert_eq!(
+        print(&format!("{} (macroexpand-1 '(inc2 x))", code)),
+        "(inc (inc x))"
+    );
+    assert_eq!(
+        print(&format!("{} (macroexpand '(inc2 x))", code)),
+        "(setq (inc x) (+ (inc x) 1))"
+    );
+    assert_eq!(
+        print(&format!("{} (macroexpand '(+ 1 2))", code)),
+        "(+ 1 2)"
+    );
+    assert_eq!(print("(macroexpand 5)"), "5");
+}
+
+#[test]
+fn test_nested_expansion() {
+    let code = "(defmacro twice (x) `(* 2 ,x))
+                (list (twice (twice 3)) '(twice 3))";
+    assert_eq!(print(code), "(12 (twice 3))");
+}
+
+#[test]
+fn test_macro_defined_after_function() {
+    let code = "(defun f (x) (double x))
+                (defmacro double (x) `(+ ,x ,x))
+                (f 4)";
+    let err = eval(code).unwrap_err();
+    assert!(matches!(err, ArrowError::InvalidArgument { .. }));
+    assert_eq!(
+        err.to_string(),
+        "invalid argument: `double` is a macro, that was defined after the call to it was expanded at 1:14"
+    );
+    // Code, that wasn't expanded, expands the macro when it runs.
+    let env = Environment::default();
+    let mut res = LispType::Bool(false);
+    for lisptype in create_lisptypes(ast(code).unwrap()).unwrap() {
+        res = lisptype.run(&env).unwrap();
+    }
+    assert_eq!(res, LispType::Integer(8));
+    let mut arrow = Arrow::default()
+        .add_function("(defmacro answer () 42)")
+        .unwrap()
+        .add_function("(defun f () (answer))")
+        .unwrap();
+    assert_eq!(arrow.run("f").unwrap(), LispType::Integer(42));
+}
+
+#[test]
+fn test_expanded_calls_keep_only_their_arguments() {
+    let code = create_lisptypes(ast("(f (+ 1 2) 'x)").unwrap()).unwrap();
+    let call = match expand(code[0].clone(), &Environment::default()).unwrap() {
+        LispType::Expression(call) => call,
+        other => panic!("expected a call, got {:?}", other),
+    };
+    assert_eq!(call.func, Func::Invoke);
+    assert_eq!(call.args.len(), 3);
+    assert_eq!(call.args[0], LispType::Symbol("f".to_string()));
+}
+
+#[test]
+fn test_only_macro_calls_keep_their_data() {
+    let compiled = |code: &str| match create_lisptypes(ast(code).unwrap()).unwrap().pop() {
+        Some(LispType::Expression(call)) => call,
+        other => panic!("expected a call, got {:?}", other),
+    };
+    // Nested calls of functions only keep their compiled arguments.
+    let call = compiled("(plain-f (plain-g (plain-h 1)))");
+    assert_eq!(call.func, Func::Invoke);
+    assert_eq!(call.args.len(), 2);
+    match &call.args[1] {
+        LispType::Expression(inner) => {
+            assert_eq!(inner.func, Func::Invoke);
+            assert_eq!(inner.args.len(), 2);
+        }
+        other => panic!("expected a call, got {:?}", other),
+    }
+    // A name, that is used for a macro, keeps the data of the call.
+    let call = compiled("(defmacro data-m (x) x) (data-m (plain-g 1))");
+    assert_eq!(call.func, Func::Call);
+    assert_eq!(call.args.len(), 3);
+    assert_eq!(
+        print("(defmacro data-m (x) `(list ',x)) (data-m (plain-g 1))"),
+        "((plain-g 1))"
+    );
+}
+
+#[test]
+fn test_endless_expansion() {
+    assert!(eval("(defmacro m (x) `(progn (m ,x))) (m 1)").is_err());
+    assert!(eval("(defmacro m () '(m)) (m)").is_err());
+}
+
+#[test]
+fn test_deeply_nested_expansion() {
+    // Every expansion nests the next call one level deeper.
+    let code = "(defmacro m (n) (if (= n 0) 0 `(+ 1 (m ,(- n 1)))))";
+    assert_eq!(
+        eval(&format!("{} (m 20)", code)).unwrap(),
+        LispType::Integer(20)
+    );
+    assert!(eval(&format!("{} (m 100000)", code)).is_err());
+    // Code nested this deep either runs or fails with an error, but it
+    // never overflows the stack.
+    let code = format!(
+        "(defmacro id (x) x) {}(id x){}",
+        "(let ((x 1)) ".repeat(250),
+        ")".repeat(250)
+    );
+    assert!(matches!(
+        eval(&code),
+        Ok(LispType::Integer(1)) | Err(ArrowError::Parse { .. }) | Err(ArrowError::Depth { .. })
+    ));
+}
+
+#[test]
+fn test_gensym() {
+    let a = eval("(gensym)").unwrap();
+    let b = eval("(gensym)").unwrap();
+    assert_ne!(a, b);
+    assert!(a.to_string().unwrap().starts_with("#:g"));
+    assert!(print("(gensym \"tmp\")").starts_with("#:tmp"));
+}
+
+#[test]
+fn test_variable_capture() {
+    // The expansion binds `tmp`, so the `tmp` of the caller can't be
+    // swapped.
+    let unhygienic = "(defmacro swap (a b)
+                        `(let ((tmp ,a)) (setq ,a ,b) (setq ,b tmp)))
+                      (let ((tmp 1) (y 2)) (swap tmp y) (list tmp y))";
+    assert_eq!(print(unhygienic), "(1 2)");
+
+    let hygienic = "(defmacro swap (a b)
+                      (let ((tmp (gensym)))
+                        `(let ((,tmp ,a)) (setq ,a ,b) (setq ,b ,tmp))))
+                    (let ((tmp 1) (y 2)) (swap tmp y) (list tmp y))";
+    assert_eq!(print(hygienic), "(2 1)");
+}
+
+#[test]
+fn test_macros_and_functions_share_names() {
+    let code = "(defun f () 1) (defmacro f () 2) (f)";
+    assert_eq!(eval(code).unwrap(), LispType::Integer(2));
+    let code = "(defmacro f () 2) (defun f () 1) (f)";
+    assert_eq!(eval(code).unwrap(), LispType::Integer(1));
+}
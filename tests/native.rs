@@ -0,0 +1,117 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use arrow::error::ArrowError;
+use arrow::expression::Arity;
+use arrow::lisptype::LispType;
+use arrow::list;
+use arrow::Arrow;
+
+fn sum(args: &[LispType]) -> Result<LispType, ArrowError> {
+    let mut res = 0;
+    for a in args {
+        res += a.int()?;
+    }
+    Ok(LispType::Integer(res))
+}
+
+#[test]
+fn test_native_functions_are_called_like_builtins() {
+    let mut arrow = Arrow::default()
+        .register_native("sum", Arity::Variadic, sum)
+        .unwrap()
+        .add_function("(defun total (xs) (apply #'sum xs))")
+        .unwrap();
+    assert_eq!(arrow.eval("(sum)").unwrap(), LispType::Integer(0));
+    assert_eq!(
+        arrow.eval("(sum 1 (sum 2 3) (* 2 2))").unwrap(),
+        LispType::Integer(10)
+    );
+    assert_eq!(
+        arrow.eval("(funcall 'sum 1 2)").unwrap(),
+        LispType::Integer(3)
+    );
+    let xs = list::list(vec![LispType::Integer(4), LispType::Integer(5)]);
+    assert_eq!(arrow.call("total", &[xs]).unwrap(), LispType::Integer(9));
+    assert_eq!(
+        arrow.call("sum", &[LispType::Integer(7)]).unwrap(),
+        LispType::Integer(7)
+    );
+    assert!(arrow.functions().contains(&"sum".to_string()));
+}
+
+#[test]
+fn test_native_errors() {
+    let mut arrow = Arrow::default()
+        .register_native("sum", Arity::Variadic, sum)
+        .unwrap()
+        .register_native("one", Arity::Exact(1), |a| Ok(a[0].clone()))
+        .unwrap();
+    assert!(matches!(
+        arrow.eval("(sum 1 \"a\")"),
+        Err(ArrowError::Type { .. })
+    ));
+    assert!(matches!(arrow.eval("(one)"), Err(ArrowError::Arity { .. })));
+    assert!(matches!(
+        arrow.eval("(one 1 2)"),
+        Err(ArrowError::Arity { .. })
+    ));
+    for name in ["+", "if", "car", "12", "\"s\""] {
+        assert!(
+            Arrow::default()
+                .register_native(name, Arity::Variadic, sum)
+                .is_err(),
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn test_native_functions_capture_state() {
+    let store: Rc<RefCell<HashMap<String, LispType>>> = Rc::default();
+    let (put, get) = (store.clone(), store.clone());
+    let mut arrow = Arrow::default()
+        .register_native("store-put", Arity::Exact(2), move |a| {
+            put.borrow_mut()
+                .insert(a[0].string()?.to_string(), a[1].clone());
+            Ok(a[1].clone())
+        })
+        .unwrap()
+        .register_native("store-get", Arity::Exact(1), move |a| {
+            let value = get.borrow().get(a[0].string()?).cloned();
+            Ok(value.unwrap_or(LispType::Bool(false)))
+        })
+        .unwrap();
+    arrow
+        .eval("(store-put \"answer\" (* 6 7)) (store-put \"list\" '(a b))")
+        .unwrap();
+    assert_eq!(store.borrow()["answer"], LispType::Integer(42));
+    assert_eq!(
+        arrow
+            .eval("(store-get \"list\")")
+            .unwrap()
+            .to_string()
+            .unwrap(),
+        "(a b)"
+    );
+    assert_eq!(
+        arrow.eval("(store-get \"missing\")").unwrap(),
+        LispType::Bool(false)
+    );
+}
+
+#[test]
+fn test_redefining_native_functions() {
+    let mut arrow = Arrow::default()
+        .register_native("f", Arity::Exact(0), |_| Ok(LispType::Integer(1)))
+        .unwrap();
+    assert_eq!(arrow.run("f").unwrap(), LispType::Integer(1));
+    arrow.eval("(defun f () 2)").unwrap();
+    assert_eq!(arrow.run("f").unwrap(), LispType::Integer(2));
+    let mut arrow = arrow
+        .register_native("f", Arity::Exact(0), |_| Ok(LispType::Integer(3)))
+        .unwrap();
+    assert_eq!(arrow.eval("(f)").unwrap(), LispType::Integer(3));
+    assert!(arrow.remove_function("f"));
+    assert!(arrow.eval("(f)").is_err());
+}
@@ -0,0 +1,264 @@
+//! Property test: no input string may make the library panic. Every
+//! failure has to be reported as an `Err`.
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use arrow::environment::Environment;
+use arrow::error::ArrowError;
+use arrow::lisptype::LispType;
+use arrow::macros::expand;
+use arrow::tokenize::{ast, create_lisptypes};
+use arrow::Arrow;
+
+/// Building blocks for the generated programs. The actaeon functions
+/// are left out, because they would open network connections.
+const VOCABULARY: &[&str] = &[
+    "(",
+    "(",
+    "(",
+    ")",
+    ")",
+    ")",
+    "[",
+    "]",
+    ";",
+    "#|",
+    "|#",
+    "'",
+    "\"",
+    " ",
+    "\n",
+    "+",
+    "-",
+    "*",
+    "/",
+    "mod",
+    "floor",
+    "sqrt",
+    "ash",
+    "logand",
+    "#x1F",
+    "99999999999",
+    "concat",
+    "equal",
+    "if",
+    "cond",
+    "case",
+    "dolist",
+    "break",
+    "<",
+    "and",
+    "print",
+    "let",
+    "progn",
+    "return",
+    "defun",
+    "lambda",
+    "`",
+    ",",
+    ",@",
+    "list",
+    "car",
+    "cons",
+    "setq",
+    "defvar",
+    "funcall",
+    "#'",
+    "defmacro",
+    "macroexpand",
+    "gensym",
+    "#s(",
+    "hash-table",
+    "data",
+    "make-hash-table",
+    "puthash",
+    "gethash",
+    "maphash",
+    ":test",
+    "aref",
+    "aset",
+    "vconcat",
+    "make-vector",
+    "substring",
+    "format",
+    "\"%s %d %.2f\"",
+    "split-string",
+    "string-to-number",
+    "\"\\n\\\"\\u00e4\"",
+    "\\",
+    "prin1-to-string",
+    "1",
+    "2.5",
+    "-3",
+    "0",
+    "t",
+    "nil",
+    "'x",
+    "'main",
+    "x",
+    "\"s\"",
+    "\"a b\"",
+    "unknown",
+    "1e400",
+    "",
+];
+
+const CHARACTERS: &[char] = &[
+    '(', ')', '[', ']', '\'', '"', ' ', '\n', '+', '-', '*', '.', '1', '0', 'e', 'a', 't', 'x',
+    'ä', '\\',
+];
+
+/// Small xorshift generator, so the test is deterministic.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 as usize
+    }
+
+    fn pick<T: Copy>(&mut self, items: &[T]) -> T {
+        items[self.next() % items.len()]
+    }
+}
+
+fn program(rng: &mut Rng) -> String {
+    let len = rng.next() % 24;
+    let mut res = String::new();
+    for _ in 0..len {
+        res.push_str(rng.pick(VOCABULARY));
+        res.push(' ');
+    }
+    res
+}
+
+fn garbage(rng: &mut Rng) -> String {
+    let len = rng.next() % 32;
+    (0..len).map(|_| rng.pick(CHARACTERS)).collect()
+}
+
+/// Run the input through every public entry point. Errors are fine,
+/// panics are not.
+fn exercise(input: &str) {
+    if let Ok(tokens) = ast(input) {
+        if let Ok(lisptypes) = create_lisptypes(tokens) {
+            for lisptype in lisptypes {
+                let env = Environment::default();
+                env.define("x", LispType::Integer(1));
+                let _ = lisptype.run(&Environment::default());
+                if let Ok(code) = expand(lisptype.clone(), &env) {
+                    let _ = code.run(&env);
+                }
+                if let Ok(res) = lisptype.run(&env) {
+                    let _ = res.num();
+                    let _ = res.bool();
+                    let _ = res.to_string();
+                }
+            }
+        }
+    }
+    if let Ok(mut arrow) = Arrow::default().add_function(input) {
+        let _ = arrow.run("'main");
+        let _ = arrow.run(input);
+    }
+    let _ = Arrow::default().run(input);
+    let _ = LispType::new(&[input.to_string()], false);
+    let _ = LispType::new(&[input.to_string()], true);
+}
+
+fn assert_no_panic(input: &str) {
+    let res = catch_unwind(AssertUnwindSafe(|| exercise(input)));
+    assert!(res.is_ok(), "input {:?} made arrow panic", input);
+}
+
+#[test]
+fn test_random_programs_dont_panic() {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    for _ in 0..20_000 {
+        assert_no_panic(&program(&mut rng));
+    }
+}
+
+#[test]
+fn test_random_characters_dont_panic() {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    for _ in 0..20_000 {
+        assert_no_panic(&garbage(&mut rng));
+    }
+}
+
+#[test]
+fn test_known_crashes_dont_panic() {
+    for input in [
+        "",
+        ")",
+        "(",
+        "42",
+        "(+ 1)",
+        "(+)",
+        "(let 'x \"a\" (+ 'x 1))",
+        "(let 'x 1 (+ 'x 1))",
+        "(defun 'main)",
+        "(progn (+ 1) 2)",
+        "(print)",
+        "(return)",
+        "(actaeon-receive 1)",
+        "('main)",
+        "()",
+        &"(".repeat(100_000),
+        &")".repeat(100_000),
+    ] {
+        assert_no_panic(input);
+    }
+}
+
+#[test]
+fn test_deep_nesting_doesnt_overflow() {
+    // Spawned threads get 2 MiB of stack by default, which is the
+    // least arrow has to work with.
+    let nested = std::thread::Builder::new()
+        .stack_size(2 * 1024 * 1024)
+        .spawn(|| {
+            for depth in [50, 100, 200, 255, 256, 1000] {
+                for (open, close) in [
+                    ("(+ 1 ", ")"),
+                    ("(let ((x 1)) ", ")"),
+                    ("(cond (t ", "))"),
+                    ("(funcall (lambda (x) x) ", ")"),
+                    ("'(", ")"),
+                    ("[", "]"),
+                    ("`(a ", ")"),
+                ] {
+                    assert_no_panic(&format!("{}1{}", open.repeat(depth), close.repeat(depth)));
+                }
+            }
+        })
+        .unwrap();
+    assert!(nested.join().is_ok());
+}
+
+#[test]
+fn test_deep_lists_dont_overflow() {
+    let nested = std::thread::Builder::new()
+        .stack_size(2 * 1024 * 1024)
+        .spawn(|| {
+            let deep = "(let ((x nil)) (dotimes (i 200000) (setq x (list x)))";
+            let mut arrow = Arrow::default();
+            assert_eq!(
+                arrow.eval(&format!("{} 1)", deep)).unwrap(),
+                LispType::Integer(1)
+            );
+            assert_eq!(
+                arrow
+                    .eval(&format!("{} (equal x (car (list x))))", deep))
+                    .unwrap(),
+                LispType::Bool(true)
+            );
+            let printed = arrow.eval(&format!("{} (prin1-to-string x))", deep));
+            assert!(matches!(printed, Err(ArrowError::Depth { .. })));
+        })
+        .unwrap();
+    assert!(nested.join().is_ok());
+}
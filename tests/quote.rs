@@ -0,0 +1,116 @@
+mod common;
+
+use arrow::error::ArrowError;
+use arrow::lisptype::LispType;
+use arrow::tokenize::{ast, create_lisptypes};
+use common::{eval, print};
+
+#[test]
+fn test_quote_atoms() {
+    assert_eq!(eval("'a").unwrap(), LispType::Symbol("a".to_string()));
+    assert_eq!(
+        eval("(quote a)").unwrap(),
+        LispType::Symbol("a".to_string())
+    );
+    assert_eq!(eval("'1").unwrap(), LispType::Integer(1));
+    assert_eq!(eval("'nil").unwrap(), LispType::Bool(false));
+    assert_eq!(print("''a"), "(quote a)");
+    assert!(matches!(
+        eval("(let ((a 1)) (+ 'a 1))"),
+        Err(ArrowError::Type { .. })
+    ));
+}
+
+#[test]
+fn test_quote_lists() {
+    assert_eq!(print("'(1 2 3)"), "(1 2 3)");
+    assert_eq!(print("'(+ 1 (* 2 x))"), "(+ 1 (* 2 x))");
+    assert_eq!(print("'((a 1) (b 2))"), "((a 1) (b 2))");
+    assert_eq!(print("'()"), "nil");
+    assert_eq!(print("(car '(f \"s\" 2.5))"), "f");
+    assert_eq!(print("(cdr (assoc 'b '((a 1) (b 2))))"), "(2)");
+    assert_eq!(
+        eval("(equal '(1 (2)) (list 1 (list 2)))").unwrap(),
+        LispType::Bool(true)
+    );
+    assert_eq!(
+        print("(let ((l '(3 4))) (dolist (x l) (push x l)) l)"),
+        "(4 3 3 4)"
+    );
+}
+
+#[test]
+fn test_backquote() {
+    assert_eq!(print("`a"), "a");
+    assert_eq!(print("`(1 2)"), "(1 2)");
+    assert_eq!(print("(let ((x 5)) `(a ,x b))"), "(a 5 b)");
+    assert_eq!(print("(let ((x 5)) `(a (,x) ,(+ x 1)))"), "(a (5) 6)");
+    assert_eq!(print("(let ((l '(1 2))) `(0 ,@l 3))"), "(0 1 2 3)");
+    assert_eq!(print("(let ((l nil)) `(,@l))"), "nil");
+    assert_eq!(print("(let ((x 1)) `,x)"), "1");
+    assert!(matches!(
+        eval("(let ((x 1)) `(,@x))"),
+        Err(ArrowError::Type { .. })
+    ));
+}
+
+#[test]
+fn test_backquote_copies_spliced_lists() {
+    let code = "(let* ((l '(1 2)) (m `(,@l))) (list (equal l m) (eq l m)))";
+    assert_eq!(print(code), "(t nil)");
+}
+
+#[test]
+fn test_dotted_lists() {
+    assert_eq!(print("'(a . b)"), "(a . b)");
+    assert_eq!(print("'(1 2 . 3)"), "(1 2 . 3)");
+    assert_eq!(print("'(1 . (2 3))"), "(1 2 3)");
+    assert_eq!(print("'(a . nil)"), "(a)");
+    assert_eq!(print("'(1 .5)"), "(1 0.5)");
+    assert_eq!(print("'[a (b . c)]"), "[a (b . c)]");
+    assert_eq!(print("(equal '(a . b) (cons 'a 'b))"), "t");
+    assert_eq!(print("(cdr (assoc 'b '((a . 1) (b . 2))))"), "2");
+    assert_eq!(print("(let ((x 2)) `(1 . ,x))"), "(1 . 2)");
+    assert_eq!(print("(let ((x '(2 3))) `(0 ,@x . 4))"), "(0 2 3 . 4)");
+    assert_eq!(print("(defmacro pair () ''(a . b)) (pair)"), "(a . b)");
+    assert!(matches!(
+        eval("(length '(1 2 . 3))"),
+        Err(ArrowError::Type { .. })
+    ));
+}
+
+#[test]
+fn test_misplaced_dots() {
+    for code in [
+        ".",
+        "'.",
+        "(. a)",
+        "'(. a)",
+        "'(a .)",
+        "'(a . b c)",
+        "'(a . . b)",
+        "'(a . b . c)",
+        "'[a . b]",
+        "(cons 1 . 2)",
+        "`(a . ,@b)",
+    ] {
+        assert!(ast(code).and_then(create_lisptypes).is_err(), "{}", code);
+    }
+}
+
+#[test]
+fn test_malformed_quotes() {
+    for code in [
+        "(f ')",
+        "'",
+        "(quote)",
+        "(quote a b)",
+        "(+ ,a)",
+        "(list ,@a)",
+        "`,@a",
+        "``a",
+        "(let 5 1)",
+    ] {
+        assert!(ast(code).and_then(create_lisptypes).is_err(), "{}", code);
+    }
+}
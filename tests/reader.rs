@@ -0,0 +1,115 @@
+use arrow::error::ArrowError;
+use arrow::lisptype::LispType;
+use arrow::tokenize::{ast, create_lisptypes};
+use arrow::Arrow;
+
+#[test]
+fn test_comments() {
+    let code = "; A comment before the code.
+                (+ 1 ; a comment inside a list
+                   2) ; a comment after the code
+                ;; (this isn't code)";
+    assert_eq!(create_lisptypes(ast(code).unwrap()).unwrap().len(), 1);
+    assert_eq!(Arrow::default().eval(code).unwrap(), LispType::Integer(3));
+    assert_eq!(
+        Arrow::default()
+            .eval("(list 'a;comment\n 'b)")
+            .unwrap()
+            .to_string()
+            .unwrap(),
+        "(a b)"
+    );
+}
+
+#[test]
+fn test_block_comments() {
+    let code = "#| A block comment
+                   with (unbalanced parens
+                   #| and a nested comment |# |#
+                (* 2 #| inline |# 3)";
+    assert_eq!(Arrow::default().eval(code).unwrap(), LispType::Integer(6));
+    assert!(matches!(
+        Arrow::default().eval("(+ 1 2) #| never closed"),
+        Err(ArrowError::Parse { .. })
+    ));
+}
+
+#[test]
+fn test_every_form_is_evaluated() {
+    let mut arrow = Arrow::default();
+    let code = "(defvar n 1) (setq n (* n 10)) (setq n (+ n 2))";
+    assert_eq!(arrow.eval(code).unwrap(), LispType::Integer(12));
+    assert_eq!(arrow.eval("").unwrap(), LispType::Bool(false));
+    assert_eq!(arrow.eval("; nothing").unwrap(), LispType::Bool(false));
+}
+
+#[test]
+fn test_forms_see_earlier_definitions() {
+    let code = "(defmacro twice (x) `(progn ,x ,x))
+                (defvar count 0)
+                (twice (setq count (+ count 1)))";
+    assert_eq!(Arrow::default().eval(code).unwrap(), LispType::Integer(2));
+}
+
+#[test]
+fn test_evaluation_stops_at_the_first_error() {
+    let mut arrow = Arrow::default();
+    let err = arrow
+        .eval("(defvar a 1)\n(car 5)\n(defvar b 2)")
+        .unwrap_err();
+    assert_eq!(err.span().unwrap().start.line, 2);
+    assert_eq!(arrow.environment().get("a"), Some(LispType::Integer(1)));
+    assert_eq!(arrow.environment().get("b"), None);
+}
+
+#[test]
+fn test_add_function_keeps_every_form() {
+    let mut arrow = Arrow::default()
+        .add_function(
+            "; Two definitions in one string.
+             (defun one () 1)
+             (defun two () (+ (one) 1))",
+        )
+        .unwrap();
+    assert_eq!(arrow.run("two").unwrap(), LispType::Integer(2));
+    assert!(Arrow::default().add_function("; only a comment").is_err());
+}
+
+#[test]
+fn test_top_level_atoms() {
+    let mut arrow = Arrow::default();
+    assert_eq!(arrow.eval("42").unwrap(), LispType::Integer(42));
+    assert_eq!(
+        arrow.eval("\"hi\"").unwrap(),
+        LispType::String("hi".to_string())
+    );
+    assert_eq!(arrow.eval(":key").unwrap().to_string().unwrap(), ":key");
+    assert_eq!(arrow.eval("nil").unwrap(), LispType::Bool(false));
+    assert_eq!(
+        arrow.eval("[1 (2)]").unwrap().to_string().unwrap(),
+        "[1 (2)]"
+    );
+    assert_eq!(arrow.eval("(defvar x 5) x").unwrap(), LispType::Integer(5));
+    let err = arrow.eval("1\n  y").unwrap_err();
+    assert!(matches!(err, ArrowError::UnboundVariable { .. }));
+    let span = err.span().unwrap();
+    assert_eq!((span.start.line, span.start.column), (2, 3));
+    assert_eq!(
+        create_lisptypes(ast("1 (+ 1 1) 'a \"s\"").unwrap())
+            .unwrap()
+            .len(),
+        4
+    );
+}
+
+#[test]
+fn test_empty_list() {
+    let mut arrow = Arrow::default();
+    assert_eq!(arrow.eval("()").unwrap(), LispType::Bool(false));
+    assert_eq!(
+        arrow.eval("(list () '())").unwrap().to_string().unwrap(),
+        "(nil nil)"
+    );
+    assert_eq!(arrow.eval("(if () 1 2)").unwrap(), LispType::Integer(2));
+    assert!(arrow.eval("(() 1)").is_err());
+}
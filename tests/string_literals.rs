@@ -0,0 +1,93 @@
+mod common;
+
+use arrow::error::ArrowError;
+use arrow::lisptype::LispType;
+use common::eval;
+
+fn string(s: &str) -> LispType {
+    LispType::String(s.to_string())
+}
+
+#[test]
+fn test_delimiters_are_stripped() {
+    assert_eq!(eval("(progn \"hello\")").unwrap(), string("hello"));
+    assert_eq!(eval("(progn \"\")").unwrap(), string(""));
+    assert_eq!(
+        eval("(progn \"a (b) [c] 'd ; e\")").unwrap(),
+        string("a (b) [c] 'd ; e")
+    );
+    assert_eq!(
+        eval("(string-length \"hello\")").unwrap(),
+        LispType::Integer(5)
+    );
+    assert_eq!(
+        eval("(equal (concat \"a\" \"b\") \"ab\")").unwrap(),
+        LispType::Bool(true)
+    );
+}
+
+#[test]
+fn test_escapes() {
+    assert_eq!(
+        eval(r#"(progn "tab\there\nquote\" backslash\\")"#).unwrap(),
+        string("tab\there\nquote\" backslash\\")
+    );
+    assert_eq!(eval(r#"(progn "ä€")"#).unwrap(), string("ä€"));
+    assert_eq!(
+        eval(r#"(string-length "ä\n")"#).unwrap(),
+        LispType::Integer(2)
+    );
+    for code in [
+        r#"(progn "\x")"#,
+        r#"(progn "\u12")"#,
+        r#"(progn "\uzzzz")"#,
+    ] {
+        assert!(
+            matches!(eval(code), Err(ArrowError::Parse { .. })),
+            "{}",
+            code
+        );
+    }
+}
+
+#[test]
+fn test_multi_line_strings() {
+    let code = "(progn \"first\n  second\")";
+    assert_eq!(eval(code).unwrap(), string("first\n  second"));
+    let code = "(list \"a\nb\"\n  (car nil))";
+    let err = eval(&format!("{} (foo)", code)).unwrap_err();
+    assert_eq!(err.span().unwrap().start.line, 3);
+}
+
+#[test]
+fn test_print_and_prin1() {
+    let value = eval(r#"(list "a \"b\"" 'c [1 "d"])"#).unwrap();
+    assert_eq!(value.to_string().unwrap(), r#"(a "b" c [1 d])"#);
+    assert_eq!(value.to_readable().unwrap(), r#"("a \"b\"" c [1 "d"])"#);
+    assert_eq!(
+        eval(r#"(prin1-to-string "back\\slash")"#).unwrap(),
+        string(r#""back\\slash""#)
+    );
+    assert_eq!(eval(r#"(prin1 "x")"#).unwrap(), string("x"));
+    assert_eq!(
+        eval(r#"(format "%s %S" "a" "a")"#).unwrap(),
+        string(r#"a "a""#)
+    );
+}
+
+#[test]
+fn test_readable_output_reads_back() {
+    let code = r#"(list "q\"uote" "new
+line" "\\" 'sym 1.5)"#;
+    let value = eval(code).unwrap();
+    let printed = value.to_readable().unwrap();
+    let read = eval(&format!("(quote {})", printed)).unwrap();
+    assert!(read.is_equal(&value), "{}", printed);
+}
+
+#[test]
+fn test_strings_in_macros() {
+    let code = r#"(defmacro greet (name) `(concat "hi \"" ,name "\""))
+                  (greet "you")"#;
+    assert_eq!(eval(code).unwrap(), string("hi \"you\""));
+}
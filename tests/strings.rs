@@ -0,0 +1,160 @@
+mod common;
+
+use arrow::error::ArrowError;
+use arrow::lisptype::LispType;
+use common::eval;
+
+/// Run the code and return the resulting string.
+fn string(code: &str) -> String {
+    match eval(code).unwrap() {
+        LispType::String(s) => s,
+        other => panic!("expected a string, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_concat() {
+    assert_eq!(string("(concat \"a\" \"b\" \"c\")"), "abc");
+    assert_eq!(string("(concat)"), "");
+    assert_eq!(string("(concat \"a\" nil \"ä\")"), "aä");
+    assert!(matches!(
+        eval("(concat \"a\" 1)"),
+        Err(ArrowError::Type { .. })
+    ));
+}
+
+#[test]
+fn test_substring() {
+    assert_eq!(string("(substring \"hello\" 1 3)"), "el");
+    assert_eq!(string("(substring \"hello\" 2)"), "llo");
+    assert_eq!(string("(substring \"hello\" -3 -1)"), "ll");
+    assert_eq!(string("(substring \"hello\" 0 nil)"), "hello");
+    assert_eq!(string("(substring \"grüße\" 2 4)"), "üß");
+    for code in [
+        "(substring \"abc\" 4)",
+        "(substring \"abc\" 2 1)",
+        "(substring \"abc\" -4)",
+    ] {
+        assert!(
+            matches!(eval(code), Err(ArrowError::InvalidArgument { .. })),
+            "{}",
+            code
+        );
+    }
+}
+
+#[test]
+fn test_length_and_case() {
+    assert_eq!(
+        eval("(string-length \"grüße\")").unwrap(),
+        LispType::Integer(5)
+    );
+    assert_eq!(eval("(string-length \"\")").unwrap(), LispType::Integer(0));
+    assert_eq!(eval("(length \"äb\")").unwrap(), LispType::Integer(2));
+    assert_eq!(eval("(length \"\")").unwrap(), LispType::Integer(0));
+    assert_eq!(string("(upcase \"straße ä\")"), "STRASSE Ä");
+    assert_eq!(string("(downcase \"ÄÖÜ Abc\")"), "äöü abc");
+    assert_eq!(string("(string-trim \"  a b \n\")"), "a b");
+}
+
+#[test]
+fn test_split_and_join() {
+    let list = |code: &str| eval(code).unwrap().to_string().unwrap();
+    assert_eq!(list("(split-string \"  two  words \")"), "(two words)");
+    assert_eq!(list("(split-string \"a,b,,c\" \",\")"), "(a b  c)");
+    assert_eq!(list("(split-string \"a,b,,c\" \",\" t)"), "(a b c)");
+    assert_eq!(list("(split-string \"\")"), "nil");
+    assert_eq!(
+        string("(string-join (split-string \"a b c\") \"-\")"),
+        "a-b-c"
+    );
+    assert_eq!(string("(string-join (list \"x\" \"y\"))"), "xy");
+    assert_eq!(string("(string-join nil \", \")"), "");
+    assert!(eval("(split-string \"abc\" \"\")").is_err());
+    assert!(eval("(string-join (list 1 2))").is_err());
+}
+
+#[test]
+fn test_prefix_suffix_and_search() {
+    assert_eq!(
+        eval("(list (string-prefix-p \"ab\" \"abc\") (string-prefix-p \"b\" \"abc\"))")
+            .unwrap()
+            .to_string()
+            .unwrap(),
+        "(t nil)"
+    );
+    assert_eq!(
+        eval("(string-suffix-p \"ßé\" \"aßé\")").unwrap(),
+        LispType::Bool(true)
+    );
+    assert_eq!(
+        eval("(string-search \"lo\" \"hello lo\")").unwrap(),
+        LispType::Integer(3)
+    );
+    assert_eq!(
+        eval("(string-search \"lo\" \"hello lo\" 4)").unwrap(),
+        LispType::Integer(6)
+    );
+    assert_eq!(
+        eval("(string-search \"ü\" \"äöü\")").unwrap(),
+        LispType::Integer(2)
+    );
+    assert_eq!(
+        eval("(string-search \"x\" \"abc\")").unwrap(),
+        LispType::Bool(false)
+    );
+}
+
+#[test]
+fn test_replace() {
+    assert_eq!(
+        string("(string-replace \"o\" \"0\" \"foo boo\")"),
+        "f00 b00"
+    );
+    assert_eq!(string("(string-replace \"ä\" \"ae\" \"bär\")"), "baer");
+    assert!(eval("(string-replace \"\" \"x\" \"abc\")").is_err());
+}
+
+#[test]
+fn test_numbers() {
+    assert_eq!(
+        eval("(string-to-number \"42\")").unwrap(),
+        LispType::Integer(42)
+    );
+    assert_eq!(
+        eval("(string-to-number \" -1.5xyz\")").unwrap(),
+        LispType::Float(-1.5)
+    );
+    assert_eq!(
+        eval("(string-to-number \"nope\")").unwrap(),
+        LispType::Integer(0)
+    );
+    assert_eq!(
+        eval("(string-to-number \"-ff\" 16)").unwrap(),
+        LispType::Integer(-255)
+    );
+    assert!(eval("(string-to-number \"1\" 40)").is_err());
+    assert_eq!(string("(number-to-string 12)"), "12");
+    assert_eq!(string("(number-to-string 1.5)"), "1.5");
+    assert_eq!(
+        eval("(string-to-number (number-to-string 0.25))").unwrap(),
+        LispType::Float(0.25)
+    );
+    assert!(eval("(number-to-string \"1\")").is_err());
+}
+
+#[test]
+fn test_format() {
+    assert_eq!(
+        string("(format \"%s: %d%% of %s\" 'progress 42.9 (list 1 2))"),
+        "progress: 42% of (1 2)"
+    );
+    assert_eq!(
+        string("(format \"%f %.1f %.0f\" 1 2.25 3.5)"),
+        "1.000000 2.2 4"
+    );
+    assert_eq!(string("(format \"[%s]\" \"ä\")"), "[ä]");
+    assert_eq!(string("(format \"no directives\")"), "no directives");
+    assert!(eval("(format \"%s %s\" 1)").is_err());
+    assert!(eval("(format \"%d\" \"1\")").is_err());
+}
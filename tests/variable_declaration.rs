@@ -1,26 +1,28 @@
+use arrow::environment::Environment;
+use arrow::error::ArrowError;
 use arrow::expression::Expression;
 use arrow::lisptype::LispType;
 
 #[test]
-fn test_variables() -> Result<(), &'static str> {
+fn test_variables() -> Result<(), ArrowError> {
     // The lisp syntax would look the following:
     // (let t 2
     //     (+ t 2))
-    let mut test = LispType::Expression(Expression::create(
+    let test = LispType::Expression(Expression::create(
         "let",
         vec![
             LispType::Symbol("t".to_string()),
-            LispType::Number(2.),
+            LispType::Integer(2),
             LispType::Expression(Expression::create(
                 "+",
-                vec![LispType::Symbol("t".to_string()), LispType::Number(2.)],
+                vec![LispType::Symbol("t".to_string()), LispType::Integer(2)],
             )?),
         ],
     )?);
 
-    let res = test.run(&mut vec![])?;
+    let res = test.run(&Environment::default())?;
 
-    assert_eq!(res.num(&mut vec![])?, 2.);
+    assert_eq!(res.num()?, 4.);
     Ok(())
 }
 
@@ -31,7 +33,7 @@ fn test_invalid_scope() {
     // (let t 2
     //     (+ t 2))
     // (+ t 3)
-    let mut test = LispType::Expression(
+    let test = LispType::Expression(
         Expression::create(
             "progn",
             vec![
@@ -40,11 +42,11 @@ fn test_invalid_scope() {
                         "let",
                         vec![
                             LispType::Symbol("t".to_string()),
-                            LispType::Number(2.),
+                            LispType::Integer(2),
                             LispType::Expression(
                                 Expression::create(
                                     "+",
-                                    vec![LispType::Symbol("t".to_string()), LispType::Number(2.)],
+                                    vec![LispType::Symbol("t".to_string()), LispType::Integer(2)],
                                 )
                                 .unwrap(),
                             ),
@@ -55,7 +57,7 @@ fn test_invalid_scope() {
                 LispType::Expression(
                     Expression::create(
                         "+",
-                        vec![LispType::Symbol("t".to_string()), LispType::Number(2.)],
+                        vec![LispType::Symbol("t".to_string()), LispType::Integer(2)],
                     )
                     .unwrap(),
                 ),
@@ -64,5 +66,5 @@ fn test_invalid_scope() {
         .unwrap(),
     );
 
-    test.run(&mut vec![]).unwrap();
+    test.run(&Environment::default()).unwrap();
 }
@@ -1,17 +1,82 @@
+mod common;
+
+use arrow::error::ArrowError;
+use arrow::lisptype::LispType;
+use arrow::tokenize::{ast, create_lisptypes};
 use arrow::Arrow;
+use common::eval;
 
 #[test]
 fn test_variable_creation() {
-    let test = "(let 'test 10)";
+    let test = "(let ((test 10)))";
     Arrow::default().add_function(test).unwrap();
 }
 
 #[test]
 fn test_variable_access() {
-    let test = "(defun 'main [] (let 'test 10 (progn (print 'test)))";
+    let test = "(defun main () (let ((test 10)) (progn (print test) test)))";
     let mut arrow = Arrow::default().add_function(test).unwrap();
+    assert_eq!(arrow.run("main").unwrap().to_string().unwrap(), "10");
+}
+
+#[test]
+fn test_let_runs_its_body() {
+    assert_eq!(eval("(let ((x 2)) (+ x 2))").unwrap(), LispType::Integer(4));
+    assert_eq!(
+        eval("(let ((a 1) (b 2)) (+ a b) (* a b))").unwrap(),
+        LispType::Integer(2)
+    );
+    assert_eq!(
+        eval("(let (a (b)) (or a b))").unwrap(),
+        LispType::Bool(false)
+    );
+    assert_eq!(eval("(let () 5)").unwrap(), LispType::Integer(5));
+}
+
+#[test]
+fn test_let_star() {
+    assert_eq!(
+        eval("(let* ((a 1) (b (+ a 1))) (* a b))").unwrap(),
+        LispType::Integer(2)
+    );
+    assert!(matches!(
+        eval("(let ((a 1) (b (+ a 1))) b)"),
+        Err(ArrowError::UnboundVariable { .. })
+    ));
+}
+
+#[test]
+fn test_shadowing() {
+    let code = "(let ((x 1)) (+ (let ((x 10)) x) x))";
+    assert_eq!(eval(code).unwrap(), LispType::Integer(11));
+    let code = "(let ((x 1)) (let ((x 2) (y x)) y))";
+    assert_eq!(eval(code).unwrap(), LispType::Integer(1));
+}
+
+#[test]
+fn test_variables_of_every_type() {
     assert_eq!(
-        arrow.run("'main").unwrap().to_string(&mut vec![]).unwrap(),
-        "10"
+        eval("(let ((b nil)) (if b 1 2))").unwrap(),
+        LispType::Integer(2)
     );
+    assert_eq!(
+        eval("(let ((s \"a\")) (concat s s))").unwrap(),
+        LispType::String("aa".to_string())
+    );
+    assert_eq!(
+        eval("(let ((x 1.5)) (equal x 1.5))").unwrap(),
+        LispType::Bool(true)
+    );
+}
+
+#[test]
+fn test_variables_are_scoped() {
+    assert!(matches!(
+        eval("(progn (let ((x 1)) x) x)"),
+        Err(ArrowError::UnboundVariable { .. })
+    ));
+    assert!(matches!(
+        ast("(let ((x 1 2)) x)").and_then(create_lisptypes),
+        Err(ArrowError::Parse { .. })
+    ));
 }
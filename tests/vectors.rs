@@ -0,0 +1,118 @@
+mod common;
+
+use arrow::error::ArrowError;
+use arrow::lisptype::LispType;
+use arrow::tokenize::ast;
+use common::{eval, print};
+
+#[test]
+fn test_vector_literals() {
+    assert_eq!(print("[1 2 3]"), "[1 2 3]");
+    assert_eq!(print("(progn [])"), "[]");
+    let nested = eval("(let ((x 1)) [x (+ x 1) [\"s\"]])").unwrap();
+    assert_eq!(nested.to_readable().unwrap(), "[x (+ x 1) [\"s\"]]");
+    assert_eq!(print("'(a [b c])"), "(a [b c])");
+    assert_eq!(print("(let ((x 1)) `[a ,x ,@(list 2 3)])"), "[a 1 2 3]");
+    assert_eq!(eval("(length [a b c])").unwrap(), LispType::Integer(3));
+}
+
+#[test]
+fn test_aref_and_aset() {
+    let code = "(setq v (make-vector 3 0))
+                (aset v 1 'x)
+                (list (aref v 0) (aref v 1) v)";
+    assert_eq!(print(code), "(0 x [0 x 0])");
+    assert_eq!(print("(aset (vector 1 2) 0 5)"), "5");
+    for code in [
+        "(aref [1 2] 2)",
+        "(aref [1 2] -1)",
+        "(aset [] 0 1)",
+        "(make-vector -1 0)",
+    ] {
+        assert!(
+            matches!(eval(code), Err(ArrowError::InvalidArgument { .. })),
+            "{}",
+            code
+        );
+    }
+    assert!(matches!(
+        eval("(aref (list 1) 0)"),
+        Err(ArrowError::Type { .. })
+    ));
+}
+
+#[test]
+fn test_vectors_are_shared() {
+    let code = "(defun fill (v) (aset v 0 'filled))
+                (let* ((v (vector nil)) (w v))
+                  (fill v)
+                  (list w (eq v w) (eq v (vector 'filled)) (equal v (vector 'filled))))";
+    assert_eq!(print(code), "([filled] t nil t)");
+}
+
+#[test]
+fn test_conversions() {
+    assert_eq!(print("(vconcat '(1 2) [3] nil)"), "[1 2 3]");
+    assert_eq!(print("(vconcat)"), "[]");
+    assert_eq!(print("(append [1 2] nil)"), "(1 2)");
+    assert_eq!(print("(append [1] '(2) [3] nil)"), "(1 2 3)");
+    assert!(matches!(eval("(vconcat 5)"), Err(ArrowError::Type { .. })));
+}
+
+#[test]
+fn test_vectors_as_hash_keys() {
+    let code = "(let ((h (make-hash-table :test 'equal)))
+                  (puthash [1 2] 'found h)
+                  (gethash (vector 1 2) h))";
+    assert_eq!(print(code), "found");
+}
+
+#[test]
+fn test_vectors_in_macros() {
+    let code = "(defmacro pair (a b) `[,a ,b]) (pair 1 x)";
+    assert_eq!(print(code), "[1 x]");
+    let code = "(defmacro v () [1 2]) (aref (v) 1)";
+    assert_eq!(print(code), "2");
+}
+
+#[test]
+fn test_malformed_vectors() {
+    for code in ["(list [1 2)", "(list 1]", "[1 2", "(list ])", "]"] {
+        assert!(ast(code).is_err(), "{}", code);
+    }
+}
+
+#[test]
+fn test_vectors_containing_themselves() {
+    let code = "(let ((a (make-vector 1 0)) (b (make-vector 1 0)))
+                  (aset a 0 a)
+                  (aset b 0 b)
+                  (list (equal a b) (equal a (vector a)) (equal a [0])))";
+    assert_eq!(print(code), "(t t nil)");
+    let code = "(let ((a (make-vector 2 0)) (b (make-vector 2 0)))
+                  (aset a 0 a)
+                  (aset b 0 b)
+                  (aset b 1 1)
+                  (equal a b))";
+    assert_eq!(print(code), "nil");
+}
+
+#[test]
+fn test_deep_vectors() {
+    let nested = std::thread::Builder::new()
+        .stack_size(2 * 1024 * 1024)
+        .spawn(|| {
+            let deep = "(let ((x nil) (y nil))
+                          (dotimes (i 200000)
+                            (setq x (vector x))
+                            (setq y (vector (list y))))";
+            assert_eq!(print(&format!("{} 1)", deep)), "1");
+            assert_eq!(
+                print(&format!("{} (equal x (aref (vector x) 0)))", deep)),
+                "t"
+            );
+            assert_eq!(print(&format!("{} (equal x y))", deep)), "nil");
+        })
+        .unwrap();
+    assert!(nested.join().is_ok());
+}